@@ -1,10 +1,14 @@
 use crate::{
-    ast::{resolve_fn, resolve_var, Args, Node, Root, Scope},
+    ast::{
+        is_lazy_fn, resolve_fn, resolve_var, Args, Bound, CompiledExpr,
+        Lookup, Node, Root, Scope, SeriesOp, Variable,
+    },
     error::Error,
+    operators::Fixity,
+    span::Span,
     tokens::{Operator, Token},
-    util::Result,
+    util::{double_factorial, factorial, Result},
 };
-use std::f64::EPSILON;
 
 pub type NumericLiteral = f64;
 
@@ -29,6 +33,10 @@ fn negate(n: f64) -> f64 {
     }
 }
 
+fn truthy(n: f64) -> bool {
+    n != 0.0
+}
+
 fn int(b: bool) -> f64 {
     if b {
         1.0
@@ -38,27 +46,50 @@ fn int(b: bool) -> f64 {
     }
 }
 
+// `%`, `mod` and `//` have no sensible result for a zero divisor, unlike `/`
+// which follows IEEE 754 and yields an infinity.
+fn divisor(args: &[NumericLiteral], span: Span) -> Result<NumericLiteral> {
+    if args[1] == 0.0 {
+        Err(Error::DivisionByZero(span))
+    }
+    else {
+        Ok(args[1])
+    }
+}
+
 pub fn eval_operator(
     operator: &Operator,
     args: &[NumericLiteral],
+    span: Span,
 ) -> EvaluationResult {
     let evaled_args = &mut args.iter();
-    let op_str = format!("{:?}", operator);
+    let missing = || Error::MissingOperands(format!("{:?}", operator), span);
+    // Only trees built by hand can lack operands
+    if args.len() < operator.num_operands() as usize {
+        return Err(missing());
+    }
 
     match operator {
         Operator::Add => Ok(evaled_args.sum()),
-        Operator::Substract => {
-            Ok(evaled_args.nth(0).ok_or(Error::MissingOperands(op_str))?
-                - evaled_args.sum::<NumericLiteral>())
-        },
+        Operator::Substract => Ok(evaled_args.next().ok_or_else(missing)?
+            - evaled_args.sum::<NumericLiteral>()),
         Operator::Multiply => Ok(evaled_args.product()),
-        Operator::Divide => {
-            Ok(evaled_args.nth(0).ok_or(Error::MissingOperands(op_str))?
-                / evaled_args.product::<NumericLiteral>())
+        Operator::Divide => Ok(evaled_args.next().ok_or_else(missing)?
+            / evaled_args.product::<NumericLiteral>()),
+        Operator::Remainder => Ok(args[0] % divisor(args, span)?),
+        Operator::Modulo => {
+            let d = divisor(args, span)?;
+            let r = args[0] % d;
+            if r != 0.0 && (r < 0.0) != (d < 0.0) {
+                Ok(r + d)
+            }
+            else {
+                Ok(r)
+            }
         },
+        Operator::FloorDivide => Ok((args[0] / divisor(args, span)?).floor()),
         Operator::Exponentiate => {
-            let base =
-                evaled_args.nth(0).ok_or(Error::MissingOperands(op_str))?;
+            let base = evaled_args.next().ok_or_else(missing)?;
             Ok(evaled_args.fold(*base, |acc, v| acc.powf(*v)))
         },
         Operator::IsGreaterThan => Ok(int(args[0] > args[1])),
@@ -67,62 +98,250 @@ pub fn eval_operator(
         Operator::IsLessThanOrEqualTo => {
             Ok(((args[0] <= args[1]) as i8).into())
         },
-        Operator::IsEqualTo => Ok(int((args[0] - args[1]).abs() < EPSILON)),
-        Operator::IsNotEqualTo => Ok(int((args[0] - args[1]).abs() > EPSILON)),
+        Operator::IsEqualTo => {
+            Ok(int((args[0] - args[1]).abs() < f64::EPSILON))
+        },
+        Operator::IsNotEqualTo => {
+            Ok(int((args[0] - args[1]).abs() > f64::EPSILON))
+        },
         Operator::Not => Ok(negate(args[0])),
+        Operator::Factorial => Ok(factorial(args[0])),
+        Operator::DoubleFactorial => Ok(double_factorial(args[0])),
+        Operator::Percent => Ok(args[0] / 100.0),
+        Operator::And => Ok(int(truthy(args[0]) && truthy(args[1]))),
+        Operator::Or => Ok(int(truthy(args[0]) || truthy(args[1]))),
+        Operator::Xor => Ok(int(truthy(args[0]) != truthy(args[1]))),
+        Operator::Conditional => {
+            Ok(if truthy(args[0]) { args[1] } else { args[2] })
+        },
     }
 }
 
 fn eval_args(
     args: &Option<Args>,
-    scope: &Scope,
+    scope: &dyn Lookup,
     fn_name: String,
+    span: Span,
 ) -> Result<Vec<NumericLiteral>> {
-    if let Some(args) = args {
-        return args.iter().map(|n| n.eval_with(scope)).collect();
+    // The parser never produces a call without arguments, but a tree built
+    // by hand may
+    match args {
+        Some(args) if !args.is_empty() => {
+            args.iter().map(|n| n.eval_in(scope)).collect()
+        },
+        _ => Err(Error::NotEnoughFunctionParams(fn_name, span)),
     }
-    Err(Error::NotEnoughFunctionParams(fn_name))
 }
 
-impl Evaluate for Node {
-    fn eval_with(&self, scope: &Scope) -> EvaluationResult {
+impl Node {
+    // `&&` and `||` only evaluate their right operand when the left one does
+    // not already decide the result.
+    fn eval_logical(
+        &self,
+        operator: &Operator,
+        scope: &dyn Lookup,
+    ) -> EvaluationResult {
+        let missing =
+            || Error::MissingOperands(operator.to_string(), self.span);
+        let mut args = self.args.iter().flatten();
+        let lhs = truthy(args.next().ok_or_else(missing)?.eval_in(scope)?);
+
+        if lhs == matches!(operator, Operator::Or) {
+            return Ok(int(lhs));
+        }
+        let rhs = args.next().ok_or_else(missing)?.eval_in(scope)?;
+        Ok(int(truthy(rhs)))
+    }
+
+    // Evaluates the condition, then only the chosen branch.
+    fn eval_conditional(
+        &self,
+        scope: &dyn Lookup,
+        invalid: impl FnOnce(usize) -> Error,
+    ) -> EvaluationResult {
+        let args = match self.args {
+            Some(ref args) if args.len() == 3 => args,
+            ref args => {
+                return Err(invalid(args.as_ref().map_or(0, |args| args.len())))
+            },
+        };
+
+        if truthy(args[0].eval_in(scope)?) {
+            args[1].eval_in(scope)
+        }
+        else {
+            args[2].eval_in(scope)
+        }
+    }
+}
+
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+impl Node {
+    // Evaluates `body` with the index set to each integer from `from` to
+    // `to`, binding it over the scope rather than copying the scope.
+    fn eval_series(
+        &self,
+        op: SeriesOp,
+        scope: &dyn Lookup,
+    ) -> EvaluationResult {
+        let f = op.name();
+        let (index, args) = match self.args {
+            Some(ref args) if args.len() == 4 => match args[0].token {
+                Token::Variable(ref index) => (index, args),
+                _ => {
+                    let span = args[0].span;
+                    return Err(Error::FunctionSyntaxError(
+                        f.to_string(),
+                        span,
+                    ));
+                },
+            },
+            Some(ref args) if args.len() > 4 => {
+                return Err(Error::FunctionSyntaxError(
+                    f.to_string(),
+                    self.span,
+                ))
+            },
+            _ => {
+                let err =
+                    Error::NotEnoughFunctionParams(f.to_string(), self.span);
+                return Err(err);
+            },
+        };
+
+        let from = args[1].eval_in(scope)?.ceil();
+        let to = args[2].eval_in(scope)?.floor();
+        // Past 2^53, `i + 1.0` is no longer a different integer
+        if !(from.abs() < MAX_EXACT_INTEGER && to.abs() < MAX_EXACT_INTEGER) {
+            return Ok(f64::NAN);
+        }
+
+        let mut result = match op {
+            SeriesOp::Sum => 0.0,
+            SeriesOp::Prod => 1.0,
+        };
+        let mut i = from;
+        while i <= to {
+            let bound = Bound {
+                name: index,
+                value: Variable::Number(i),
+                parent: scope,
+            };
+            let term = args[3].eval_in(&bound)?;
+            match op {
+                SeriesOp::Sum => result += term,
+                SeriesOp::Prod => result *= term,
+            }
+            i += 1.0;
+        }
+        Ok(result)
+    }
+
+    fn eval_in(&self, scope: &dyn Lookup) -> EvaluationResult {
         match self.token {
+            Token::Operator(ref operator @ (Operator::And | Operator::Or)) => {
+                self.eval_logical(operator, scope)
+            },
+            Token::Operator(Operator::Conditional) => self
+                .eval_conditional(scope, |_| {
+                    Error::MissingOperands("?:".to_string(), self.span)
+                }),
+            Token::CustomOperator(ref custom) => {
+                let operands = match custom.fixity {
+                    Fixity::Infix => 2,
+                    Fixity::Prefix | Fixity::Postfix => 1,
+                };
+                let args = match self.args {
+                    Some(ref args) if args.len() == operands => args
+                        .iter()
+                        .map(|node| node.eval_in(scope))
+                        .collect::<Result<Vec<NumericLiteral>>>()?,
+                    _ => {
+                        return Err(Error::MissingOperands(
+                            custom.symbol.clone(),
+                            self.span,
+                        ))
+                    },
+                };
+                (custom.eval)(&args)
+            },
+            Token::Function(ref f) if is_lazy_fn(f) => {
+                self.eval_conditional(scope, |count| match count {
+                    0..=2 => {
+                        Error::NotEnoughFunctionParams(f.clone(), self.span)
+                    },
+                    _ => Error::FunctionSyntaxError(f.clone(), self.span),
+                })
+            },
+            Token::Series(op) => self.eval_series(op, scope),
             Token::Operator(ref operator) => {
-                dbg!(&self.args);
                 let args = self
                     .args
                     .as_ref()
-                    .ok_or(Error::MissingOperands(format!("{:?}", operator)))?
+                    .ok_or_else(|| {
+                        Error::MissingOperands(
+                            format!("{:?}", operator),
+                            self.span,
+                        )
+                    })?
                     .iter()
-                    .map(|node| node.eval_with(scope))
+                    .map(|node| node.eval_in(scope))
                     .collect::<Result<Vec<NumericLiteral>>>()?;
 
-                dbg!(&args);
-                let res = eval_operator(&operator, &args);
-                dbg!(res)
+                eval_operator(operator, &args, self.span)
             },
             Token::Function(ref f) => {
-                let args = eval_args(&self.args, scope, f.clone())?;
-                resolve_fn(f, scope)?(&args)
+                let args = eval_args(&self.args, scope, f.clone(), self.span)?;
+                let result = resolve_fn(f, scope).ok_or_else(|| {
+                    Error::UnknownFunction(f.clone(), self.span)
+                })?(&args);
+                result.map_err(|err| match err {
+                    Error::NotEnoughFunctionParams(f, _) => {
+                        Error::NotEnoughFunctionParams(f, self.span)
+                    },
+                    err => err,
+                })
             },
 
             Token::Number(num) => Ok(num),
-            Token::Variable(ref var) => resolve_var(&var, scope),
-            _ => Err(Error::CannotEvaluateToken(format!("{:?}", self.token))),
+            Token::Error(ref err) => Err(err.clone()),
+            Token::Variable(ref var) => resolve_var(var, scope)
+                .ok_or_else(|| Error::UnknownVariable(var.clone(), self.span)),
+            _ => Err(Error::CannotEvaluateToken(
+                format!("{:?}", self.token),
+                self.span,
+            )),
         }
     }
+}
+
+impl Evaluate for Node {
+    fn eval_with(&self, scope: &Scope) -> EvaluationResult {
+        self.eval_in(scope)
+    }
 
     fn eval(&self) -> EvaluationResult {
         self.eval_with(&Scope::new())
     }
 }
 
-impl<'a> Evaluate for Root<'a> {
+impl Evaluate for CompiledExpr {
     fn eval(&self) -> EvaluationResult {
-        self.node.eval_with(self.scope)
+        self.node.eval()
     }
 
     fn eval_with(&self, scope: &Scope) -> EvaluationResult {
         self.node.eval_with(scope)
     }
 }
+
+impl<'a> Evaluate for Root<'a> {
+    fn eval(&self) -> EvaluationResult {
+        self.expr.eval_with(self.scope)
+    }
+
+    fn eval_with(&self, scope: &Scope) -> EvaluationResult {
+        self.expr.eval_with(scope)
+    }
+}
@@ -0,0 +1,394 @@
+use crate::{
+    ast::{Args, CompiledExpr, Node, NumericLiteral},
+    error::Error,
+    operators::CustomOperator,
+    span::Span,
+    tokens::{Operator, Token},
+};
+
+/// A parsed expression, as returned by `CompiledExpr::to_expr`.
+///
+/// A unary minus is a `UnaryOp::Neg`, and a multiplication is a
+/// `BinaryOp::Mul` whether it was written out or implied, as in `2x`. An
+/// `Expr` converts back into a `CompiledExpr` for evaluation.
+///
+/// ```
+/// use asciimath::{compile, scope, BinaryOp, Expr};
+///
+/// let expr = compile("2x + 1", &scope! {}).unwrap().to_expr();
+/// let two_x = Expr::Binary {
+///     op: BinaryOp::Mul,
+///     lhs: Box::new(Expr::Num(2.0)),
+///     rhs: Box::new(Expr::Var("x".to_string())),
+/// };
+///
+/// assert_eq!(
+///     expr,
+///     Expr::Binary {
+///         op: BinaryOp::Add,
+///         lhs: Box::new(two_x),
+///         rhs: Box::new(Expr::Num(1.0)),
+///     }
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Num(NumericLiteral),
+    Var(String),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// `cond ? then : otherwise`
+    Conditional {
+        cond: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+    /// A builtin or user-defined function, including `if` and the `abs`
+    /// written as `|x|`.
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// A sum or product of `body` for each integer `index` from `from` to
+    /// `to`, as in `sum_(i=1)^n i^2`. Only the AsciiMath and LaTeX syntaxes
+    /// read it.
+    Series {
+        op: SeriesOp,
+        index: String,
+        from: Box<Expr>,
+        to: Box<Expr>,
+        body: Box<Expr>,
+    },
+    /// An operator defined through `ParserBuilder`, with one operand if it is
+    /// a prefix or postfix operator and two otherwise.
+    Custom {
+        op: CustomOperator,
+        args: Vec<Expr>,
+    },
+    /// Placeholder for input that failed to parse, only found in trees
+    /// returned by `compile_recovering`.
+    Error(Error),
+}
+
+/// An operator with a single operand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `-x`
+    Neg,
+    /// `!x`
+    Not,
+    /// `x!`
+    Factorial,
+    /// `x!!`
+    DoubleFactorial,
+    /// `x%`
+    Percent,
+}
+
+/// An operator with two operands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// `%`, the remainder of a truncated division
+    Rem,
+    /// `mod`, the remainder taking the sign of the divisor
+    Mod,
+    /// `//`
+    FloorDiv,
+    Pow,
+    Gt,
+    Lt,
+    Ge,
+    Le,
+    Eq,
+    Ne,
+    And,
+    Or,
+    Xor,
+}
+
+/// The operation folding the terms of an `Expr::Series`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeriesOp {
+    /// `sum`, starting from 0
+    Sum,
+    /// `prod`, starting from 1
+    Prod,
+}
+
+impl UnaryOp {
+    /// Whether the operator follows its operand, as in `5!`.
+    pub fn is_postfix(self) -> bool {
+        !matches!(self, UnaryOp::Neg | UnaryOp::Not)
+    }
+
+    fn from_operator(operator: Operator) -> Option<UnaryOp> {
+        match operator {
+            Operator::Not => Some(UnaryOp::Not),
+            Operator::Factorial => Some(UnaryOp::Factorial),
+            Operator::DoubleFactorial => Some(UnaryOp::DoubleFactorial),
+            Operator::Percent => Some(UnaryOp::Percent),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn operator(self) -> Operator {
+        match self {
+            UnaryOp::Neg => Operator::Substract,
+            UnaryOp::Not => Operator::Not,
+            UnaryOp::Factorial => Operator::Factorial,
+            UnaryOp::DoubleFactorial => Operator::DoubleFactorial,
+            UnaryOp::Percent => Operator::Percent,
+        }
+    }
+}
+
+impl BinaryOp {
+    fn from_operator(operator: Operator) -> Option<BinaryOp> {
+        match operator {
+            Operator::Add => Some(BinaryOp::Add),
+            Operator::Substract => Some(BinaryOp::Sub),
+            Operator::Multiply => Some(BinaryOp::Mul),
+            Operator::Divide => Some(BinaryOp::Div),
+            Operator::Remainder => Some(BinaryOp::Rem),
+            Operator::Modulo => Some(BinaryOp::Mod),
+            Operator::FloorDivide => Some(BinaryOp::FloorDiv),
+            Operator::Exponentiate => Some(BinaryOp::Pow),
+            Operator::IsGreaterThan => Some(BinaryOp::Gt),
+            Operator::IsLessThan => Some(BinaryOp::Lt),
+            Operator::IsGreaterThanOrEqualTo => Some(BinaryOp::Ge),
+            Operator::IsLessThanOrEqualTo => Some(BinaryOp::Le),
+            Operator::IsEqualTo => Some(BinaryOp::Eq),
+            Operator::IsNotEqualTo => Some(BinaryOp::Ne),
+            Operator::And => Some(BinaryOp::And),
+            Operator::Or => Some(BinaryOp::Or),
+            Operator::Xor => Some(BinaryOp::Xor),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn operator(self) -> Operator {
+        match self {
+            BinaryOp::Add => Operator::Add,
+            BinaryOp::Sub => Operator::Substract,
+            BinaryOp::Mul => Operator::Multiply,
+            BinaryOp::Div => Operator::Divide,
+            BinaryOp::Rem => Operator::Remainder,
+            BinaryOp::Mod => Operator::Modulo,
+            BinaryOp::FloorDiv => Operator::FloorDivide,
+            BinaryOp::Pow => Operator::Exponentiate,
+            BinaryOp::Gt => Operator::IsGreaterThan,
+            BinaryOp::Lt => Operator::IsLessThan,
+            BinaryOp::Ge => Operator::IsGreaterThanOrEqualTo,
+            BinaryOp::Le => Operator::IsLessThanOrEqualTo,
+            BinaryOp::Eq => Operator::IsEqualTo,
+            BinaryOp::Ne => Operator::IsNotEqualTo,
+            BinaryOp::And => Operator::And,
+            BinaryOp::Or => Operator::Or,
+            BinaryOp::Xor => Operator::Xor,
+        }
+    }
+}
+
+impl SeriesOp {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            SeriesOp::Sum => "sum",
+            SeriesOp::Prod => "prod",
+        }
+    }
+}
+
+impl Expr {
+    /// The direct subexpressions, in the order they were written.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Num(_) | Expr::Var(_) | Expr::Error(_) => Vec::new(),
+            Expr::Unary { operand, .. } => vec![operand],
+            Expr::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Expr::Conditional {
+                cond,
+                then,
+                otherwise,
+            } => vec![cond, then, otherwise],
+            Expr::Series { from, to, body, .. } => vec![from, to, body],
+            Expr::Call { args, .. } | Expr::Custom { args, .. } => {
+                args.iter().collect()
+            },
+        }
+    }
+
+    /// See `children`.
+    pub fn children_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Expr::Num(_) | Expr::Var(_) | Expr::Error(_) => Vec::new(),
+            Expr::Unary { operand, .. } => vec![operand],
+            Expr::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Expr::Conditional {
+                cond,
+                then,
+                otherwise,
+            } => vec![cond, then, otherwise],
+            Expr::Series { from, to, body, .. } => vec![from, to, body],
+            Expr::Call { args, .. } | Expr::Custom { args, .. } => {
+                args.iter_mut().collect()
+            },
+        }
+    }
+}
+
+impl CompiledExpr {
+    /// The expression as an `Expr` tree, to inspect or rewrite it.
+    pub fn to_expr(&self) -> Expr {
+        Expr::from(&self.node)
+    }
+}
+
+impl From<Expr> for CompiledExpr {
+    /// Compiles a tree back for evaluation. Errors then point at an empty
+    /// span, since the tree has no source text.
+    fn from(expr: Expr) -> CompiledExpr {
+        CompiledExpr {
+            node: Node::from(expr),
+        }
+    }
+}
+
+impl From<&Node> for Expr {
+    fn from(node: &Node) -> Expr {
+        let mut args: Vec<Expr> =
+            node.args.iter().flatten().map(Expr::from).collect();
+        let malformed = || {
+            let token = format!("{:?}", node.token);
+            Expr::Error(Error::CannotEvaluateToken(token, node.span))
+        };
+
+        match node.token {
+            Token::Number(n) => Expr::Num(n),
+            Token::Variable(ref name) => Expr::Var(name.clone()),
+            Token::Function(ref name) => Expr::Call {
+                name: name.clone(),
+                args,
+            },
+            Token::Series(op) if args.len() == 4 => match args[0] {
+                Expr::Var(ref index) => {
+                    let index = index.clone();
+                    let body = Box::new(args.pop().unwrap());
+                    let to = Box::new(args.pop().unwrap());
+                    let from = Box::new(args.pop().unwrap());
+                    Expr::Series {
+                        op,
+                        index,
+                        from,
+                        to,
+                        body,
+                    }
+                },
+                _ => malformed(),
+            },
+            Token::CustomOperator(ref op) => Expr::Custom {
+                op: op.clone(),
+                args,
+            },
+            Token::Error(ref err) => Expr::Error(err.clone()),
+            // The parser turns `-x` into `-1 * x`
+            Token::Operator(Operator::Multiply)
+                if args.len() == 2 && args[0] == Expr::Num(-1.0) =>
+            {
+                Expr::Unary {
+                    op: UnaryOp::Neg,
+                    operand: Box::new(args.pop().unwrap()),
+                }
+            },
+            Token::Operator(Operator::Conditional) if args.len() == 3 => {
+                let otherwise = Box::new(args.pop().unwrap());
+                let then = Box::new(args.pop().unwrap());
+                let cond = Box::new(args.pop().unwrap());
+                Expr::Conditional {
+                    cond,
+                    then,
+                    otherwise,
+                }
+            },
+            Token::Operator(operator) if args.len() == 1 => {
+                match UnaryOp::from_operator(operator) {
+                    Some(op) => Expr::Unary {
+                        op,
+                        operand: Box::new(args.pop().unwrap()),
+                    },
+                    None => malformed(),
+                }
+            },
+            Token::Operator(operator) if args.len() == 2 => {
+                match BinaryOp::from_operator(operator) {
+                    Some(op) => {
+                        let rhs = Box::new(args.pop().unwrap());
+                        let lhs = Box::new(args.pop().unwrap());
+                        Expr::Binary { op, lhs, rhs }
+                    },
+                    None => malformed(),
+                }
+            },
+            _ => malformed(),
+        }
+    }
+}
+
+impl From<Expr> for Node {
+    fn from(expr: Expr) -> Node {
+        let span = Span::default();
+        let node = |token, args: Vec<Expr>| {
+            let args: Args = args.into_iter().map(Node::from).collect();
+            Node::new(token, Some(args), span)
+        };
+
+        match expr {
+            Expr::Num(n) => Node::new(Token::Number(n), None, span),
+            Expr::Var(name) => Node::new(Token::Variable(name), None, span),
+            Expr::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => node(
+                Token::Operator(Operator::Multiply),
+                vec![Expr::Num(-1.0), *operand],
+            ),
+            Expr::Unary { op, operand } => {
+                node(Token::Operator(op.operator()), vec![*operand])
+            },
+            Expr::Binary { op, lhs, rhs } => {
+                node(Token::Operator(op.operator()), vec![*lhs, *rhs])
+            },
+            Expr::Conditional {
+                cond,
+                then,
+                otherwise,
+            } => node(
+                Token::Operator(Operator::Conditional),
+                vec![*cond, *then, *otherwise],
+            ),
+            Expr::Call { name, args } => node(Token::Function(name), args),
+            Expr::Series {
+                op,
+                index,
+                from,
+                to,
+                body,
+            } => node(
+                Token::Series(op),
+                vec![Expr::Var(index), *from, *to, *body],
+            ),
+            Expr::Custom { op, args } => node(Token::CustomOperator(op), args),
+            Expr::Error(err) => Node::new(Token::Error(err), None, span),
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use crate::{
+    ast::{
+        print::{binding, is_negation, is_prefix, scientific, ATOM},
+        BinaryOp, CompiledExpr, Expr, NumericLiteral, UnaryOp,
+    },
+    constants::{canonical_name, is_greek_name},
+    operators::{Associativity, Fixity},
+    tokens::Operator,
+};
+use std::slice;
+
+impl Expr {
+    /// Typesets the expression as LaTeX math, without the surrounding `$`.
+    ///
+    /// Builtin functions and constants get their usual notation, such as
+    /// `\frac`, `\sqrt` and `\pi`. Other functions are set with
+    /// `\operatorname`, and multi-letter variables upright.
+    ///
+    /// ```
+    /// use asciimath::{compile, Scope};
+    ///
+    /// let scope = Scope::new();
+    /// let expr = compile("sqrt(x^2 + 1) / (2PI)", &scope).unwrap();
+    /// assert_eq!(r"\frac{\sqrt{x^{2} + 1}}{2\pi}", expr.to_latex());
+    ///
+    /// let expr = compile("|x_1 - 3| <= 2", &scope).unwrap();
+    /// assert_eq!(r"\left|x_{1} - 3\right| \leq 2", expr.to_latex());
+    /// ```
+    pub fn to_latex(&self) -> String {
+        match self {
+            Expr::Num(n) => number(*n),
+            Expr::Var(name) => variable(name),
+            Expr::Error(_) => "?".to_string(),
+            Expr::Call { name, args } => call(name, args),
+            Expr::Series {
+                op,
+                index,
+                from,
+                to,
+                body,
+            } => {
+                let parens = level(body) < Operator::Multiply.get_precedence();
+                format!(
+                    r"\{}_{{{} = {}}}^{{{}}} {}",
+                    op.name(),
+                    variable(index),
+                    from.to_latex(),
+                    to.to_latex(),
+                    group(body, parens)
+                )
+            },
+            Expr::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => {
+                // `-6.02 \times 10^{23}` reads the same either way
+                let number = matches!(**operand, Expr::Num(_));
+                let parens = !number
+                    && level(operand) < Operator::Exponentiate.get_precedence()
+                    || negative(operand);
+                format!("-{}", group(operand, parens))
+            },
+            Expr::Unary {
+                op: UnaryOp::Not,
+                operand,
+            } => prefix(r"\neg ", Operator::Not.get_precedence(), operand),
+            Expr::Unary { op, operand } => {
+                let symbol = match op {
+                    UnaryOp::Percent => r"\%",
+                    UnaryOp::DoubleFactorial => "!!",
+                    _ => "!",
+                };
+                postfix(symbol, operand)
+            },
+            Expr::Binary { op, lhs, rhs } => binary(*op, lhs, rhs),
+            Expr::Conditional {
+                cond,
+                then,
+                otherwise,
+            } => cases(cond, then, otherwise),
+            Expr::Custom { op, args } => {
+                let word = op.symbol.starts_with(char::is_alphabetic);
+                match (op.fixity, args.as_slice()) {
+                    (Fixity::Prefix, [operand]) if word => {
+                        let symbol =
+                            format!(r"\operatorname{{{}}} ", op.symbol);
+                        prefix(&symbol, op.precedence, operand)
+                    },
+                    (Fixity::Prefix, [operand]) => {
+                        prefix(&escape(&op.symbol), op.precedence, operand)
+                    },
+                    (Fixity::Postfix, [operand]) if word => postfix(
+                        &format!(r"\ \mathrm{{{}}}", op.symbol),
+                        operand,
+                    ),
+                    (Fixity::Postfix, [operand]) => {
+                        postfix(&escape(&op.symbol), operand)
+                    },
+                    (Fixity::Infix, [lhs, rhs]) => {
+                        let symbol = if word {
+                            format!(r"\mathbin{{\mathrm{{{}}}}}", op.symbol)
+                        }
+                        else {
+                            format!(r"\mathbin{{{}}}", escape(&op.symbol))
+                        };
+                        let right = op.associativity == Associativity::Right;
+                        infix(&symbol, op.precedence, right, lhs, rhs)
+                    },
+                    _ => call(&op.symbol, args),
+                }
+            },
+        }
+    }
+}
+
+impl CompiledExpr {
+    /// See `Expr::to_latex`.
+    pub fn to_latex(&self) -> String {
+        self.to_expr().to_latex()
+    }
+}
+
+// Fractions, functions and `cases` are delimited on their own, so they only
+// need parentheses before `^` and postfix operators.
+pub(super) fn level(expr: &Expr) -> i8 {
+    match expr {
+        Expr::Var(name) if name == "NEG_INFINITY" => binding(&Expr::Num(-1.0)),
+        // Set as a product, as in `1 \times 10^{300}`
+        Expr::Num(n) if !negative(expr) && scientific(*n).is_some() => {
+            Operator::Multiply.get_precedence()
+        },
+        Expr::Binary {
+            op: BinaryOp::Div, ..
+        }
+        | Expr::Binary {
+            op: BinaryOp::FloorDiv,
+            ..
+        }
+        | Expr::Conditional { .. } => ATOM,
+        _ => binding(expr),
+    }
+}
+
+pub(super) fn negative(expr: &Expr) -> bool {
+    is_negation(expr) || *expr == Expr::Var("NEG_INFINITY".to_string())
+}
+
+// Whether the expression can carry an exponent or a postfix operator as it
+// is: `x^{2}`, `\sqrt{x}^{2}` or `5!`.
+pub(super) fn is_simple(expr: &Expr) -> bool {
+    match expr {
+        Expr::Num(n) => !negative(expr) && scientific(*n).is_none(),
+        Expr::Var(_) => !negative(expr),
+        Expr::Call { .. } | Expr::Series { .. } | Expr::Error(_) => true,
+        _ => false,
+    }
+}
+
+fn group(expr: &Expr, parens: bool) -> String {
+    if parens {
+        format!(r"\left({}\right)", expr.to_latex())
+    }
+    else {
+        expr.to_latex()
+    }
+}
+
+fn number(n: NumericLiteral) -> String {
+    if n.is_nan() {
+        r"\mathrm{NaN}".to_string()
+    }
+    else if n.is_infinite() {
+        let sign = if n < 0.0 { "-" } else { "" };
+        format!(r"{}\infty", sign)
+    }
+    else if let Some((mantissa, exponent)) = scientific(n) {
+        format!(r"{} \times 10^{{{}}}", mantissa, exponent)
+    }
+    else {
+        n.to_string()
+    }
+}
+
+fn variable(name: &str) -> String {
+    match name {
+        "PI" => r"\pi".to_string(),
+        // Upright, unlike a variable named `e`
+        "E" => r"\mathrm{e}".to_string(),
+        "INFINITY" => r"\infty".to_string(),
+        "NEG_INFINITY" => r"-\infty".to_string(),
+        _ => identifier(&canonical_name(name)),
+    }
+}
+
+// `theta_max` and `θ_max` are set as `\theta_{\mathrm{max}}`.
+fn identifier(name: &str) -> String {
+    let (base, subscript) = match name.split_once('_') {
+        Some((base, subscript)) if !base.is_empty() => (base, Some(subscript)),
+        _ => (name, None),
+    };
+
+    // Omicron has no command, as it looks just like a Latin `o`
+    let base = if base == "omicron" {
+        "o".to_string()
+    }
+    else if is_greek_name(base) {
+        format!(r"\{}", base)
+    }
+    else if base.chars().count() == 1 {
+        base.to_string()
+    }
+    else {
+        format!(r"\mathrm{{{}}}", escape(base))
+    };
+
+    match subscript {
+        Some(subscript) if !subscript.is_empty() => {
+            format!("{}_{{{}}}", base, identifier(subscript))
+        },
+        _ => base,
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '_' | '%' | '&' | '#' | '$' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '^' => escaped.push_str(r"\^{}"),
+            '~' => escaped.push_str(r"\textasciitilde{}"),
+            '\\' => escaped.push_str(r"\backslash "),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn arguments(args: &[Expr]) -> String {
+    let args: Vec<String> = args.iter().map(Expr::to_latex).collect();
+    format!(r"\left({}\right)", args.join(", "))
+}
+
+fn call(name: &str, args: &[Expr]) -> String {
+    match (name, args) {
+        ("sqrt", [x]) => format!(r"\sqrt{{{}}}", x.to_latex()),
+        ("cbrt", [x]) => format!(r"\sqrt[3]{{{}}}", x.to_latex()),
+        ("abs", [x]) => format!(r"\left|{}\right|", x.to_latex()),
+        ("floor", [x]) => {
+            format!(r"\left\lfloor {} \right\rfloor", x.to_latex())
+        },
+        ("ceil", [x]) => format!(r"\left\lceil {} \right\rceil", x.to_latex()),
+        ("log", [base, x]) => {
+            format!(
+                r"\log_{{{}}}{}",
+                base.to_latex(),
+                arguments(slice::from_ref(x))
+            )
+        },
+        ("log" | "log_10", [_]) => format!(r"\log_{{10}}{}", arguments(args)),
+        ("if", [cond, then, otherwise]) => cases(cond, then, otherwise),
+        ("sin" | "cos" | "tan" | "ln" | "min" | "max", _) => {
+            format!(r"\{}{}", name, arguments(args))
+        },
+        _ => format!(r"\operatorname{{{}}}{}", escape(name), arguments(args)),
+    }
+}
+
+fn cases(cond: &Expr, then: &Expr, otherwise: &Expr) -> String {
+    format!(
+        r"\begin{{cases}} {} & \text{{if }} {} \\ {} & \text{{otherwise}} \end{{cases}}",
+        then.to_latex(),
+        cond.to_latex(),
+        otherwise.to_latex()
+    )
+}
+
+// Like the parser, a prefix operator takes everything binding tighter than
+// itself.
+fn prefix(symbol: &str, precedence: i8, operand: &Expr) -> String {
+    let parens = level(operand) <= precedence && !is_prefix(operand);
+    format!("{}{}", symbol, group(operand, parens))
+}
+
+fn postfix(symbol: &str, operand: &Expr) -> String {
+    format!("{}{}", group(operand, !is_simple(operand)), symbol)
+}
+
+fn binary(op: BinaryOp, lhs: &Expr, rhs: &Expr) -> String {
+    let (lhs_text, rhs_text) = (lhs.to_latex(), rhs.to_latex());
+    match op {
+        BinaryOp::Div => format!(r"\frac{{{}}}{{{}}}", lhs_text, rhs_text),
+        BinaryOp::FloorDiv => format!(
+            r"\left\lfloor \frac{{{}}}{{{}}} \right\rfloor",
+            lhs_text, rhs_text
+        ),
+        BinaryOp::Pow => {
+            format!("{}^{{{}}}", group(lhs, !is_simple(lhs)), rhs_text)
+        },
+        BinaryOp::Mul if juxtaposed(lhs, rhs) => {
+            format!("{}{}", lhs_text, rhs_text)
+        },
+        _ => {
+            let operator = op.operator();
+            infix(
+                &symbol(op),
+                operator.get_precedence(),
+                operator.is_right_associative(),
+                lhs,
+                rhs,
+            )
+        },
+    }
+}
+
+fn symbol(op: BinaryOp) -> String {
+    match op {
+        BinaryOp::Mul => r"\cdot".to_string(),
+        BinaryOp::Rem => r"\mathbin{\%}".to_string(),
+        BinaryOp::Mod => r"\bmod".to_string(),
+        BinaryOp::Ge => r"\geq".to_string(),
+        BinaryOp::Le => r"\leq".to_string(),
+        BinaryOp::Eq => "=".to_string(),
+        BinaryOp::Ne => r"\neq".to_string(),
+        BinaryOp::And => r"\land".to_string(),
+        BinaryOp::Or => r"\lor".to_string(),
+        BinaryOp::Xor => r"\oplus".to_string(),
+        _ => op.operator().to_string(),
+    }
+}
+
+fn infix(
+    symbol: &str,
+    precedence: i8,
+    right_associative: bool,
+    lhs: &Expr,
+    rhs: &Expr,
+) -> String {
+    let lhs_text = lhs.to_latex();
+    // `x\% - 1` would read as the remainder of `x` and `-1`
+    let lhs_parens = level(lhs) < precedence
+        || (right_associative && level(lhs) == precedence)
+        || (symbol == "-" && lhs_text.ends_with(r"\%"));
+    // `a - -b` reads poorly in print, unlike in a text editor
+    let rhs_parens = negative(rhs)
+        || level(rhs) < precedence
+        || (!right_associative && level(rhs) == precedence);
+
+    let lhs_text = if lhs_parens {
+        format!(r"\left({}\right)", lhs_text)
+    }
+    else {
+        lhs_text
+    };
+    format!("{} {} {}", lhs_text, symbol, group(rhs, rhs_parens))
+}
+
+// A number before a name or a function is written without a sign, as in
+// `2x` or `3\sqrt{2}`.
+pub(super) fn juxtaposed(lhs: &Expr, rhs: &Expr) -> bool {
+    let named = |expr: &Expr| {
+        matches!(expr, Expr::Var(_) | Expr::Call { .. } | Expr::Series { .. })
+            && !negative(expr)
+    };
+    let number = matches!(lhs, Expr::Num(n)
+        if n.is_finite() && *n >= 0.0 && scientific(*n).is_none());
+
+    number
+        && match rhs {
+            Expr::Binary {
+                op: BinaryOp::Pow,
+                lhs,
+                ..
+            } => named(lhs),
+            _ => named(rhs),
+        }
+}
@@ -0,0 +1,348 @@
+use crate::{
+    ast::{
+        latex::{is_simple, juxtaposed, level, negative},
+        print::{is_prefix, scientific},
+        BinaryOp, CompiledExpr, Expr, NumericLiteral, SeriesOp, UnaryOp,
+    },
+    constants::{canonical_name, greek_glyph},
+    operators::{Associativity, Fixity},
+    tokens::Operator,
+};
+
+// Separates a function name from its argument.
+const APPLY: &str = "<mo>&#x2061;</mo>";
+
+// Marks a multiplication written without a sign, as in `2x`.
+const TIMES: &str = "<mo>&#x2062;</mo>";
+
+impl Expr {
+    /// Serializes the expression as Presentation MathML, for embedding in
+    /// HTML or EPUB.
+    ///
+    /// Every node is a single element, grouped with `<mrow>` where needed,
+    /// and text is escaped.
+    ///
+    /// ```
+    /// use asciimath::{compile, Scope};
+    ///
+    /// let scope = Scope::new();
+    /// let expr = compile("x^2 < 1", &scope).unwrap();
+    /// assert_eq!(
+    ///     expr.to_mathml(),
+    ///     "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">\
+    ///      <mrow><msup><mi>x</mi><mn>2</mn></msup><mo>&lt;</mo><mn>1</mn></mrow>\
+    ///      </math>"
+    /// );
+    /// ```
+    pub fn to_mathml(&self) -> String {
+        format!(
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{}</math>",
+            element(self)
+        )
+    }
+}
+
+impl CompiledExpr {
+    /// See `Expr::to_mathml`.
+    pub fn to_mathml(&self) -> String {
+        self.to_expr().to_mathml()
+    }
+}
+
+fn element(expr: &Expr) -> String {
+    match expr {
+        Expr::Num(n) => number(*n),
+        Expr::Var(name) => variable(name),
+        Expr::Error(_) => "<merror><mtext>?</mtext></merror>".to_string(),
+        Expr::Call { name, args } => call(name, args),
+        Expr::Series {
+            op,
+            index,
+            from,
+            to,
+            body,
+        } => {
+            let symbol = match op {
+                SeriesOp::Sum => "\u{2211}",
+                SeriesOp::Prod => "\u{220f}",
+            };
+            let lower = mrow(&[variable(index), mo("="), element(from)]);
+            let parens = level(body) < Operator::Multiply.get_precedence();
+            let series = format!(
+                "<munderover>{}{}{}</munderover>",
+                mo(symbol),
+                lower,
+                element(to)
+            );
+            mrow(&[series, group(body, parens)])
+        },
+        Expr::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => {
+            let number = matches!(**operand, Expr::Num(_));
+            let parens = !number
+                && level(operand) < Operator::Exponentiate.get_precedence()
+                || negative(operand);
+            let minus = mo(operator(Operator::Substract));
+            mrow(&[minus, group(operand, parens)])
+        },
+        Expr::Unary { op, operand } => {
+            let symbol = mo(operator(op.operator()));
+            if op.is_postfix() {
+                mrow(&[group(operand, !is_simple(operand)), symbol])
+            }
+            else {
+                prefix(symbol, op.operator().get_precedence(), operand)
+            }
+        },
+        Expr::Binary { op, lhs, rhs } => binary(*op, lhs, rhs),
+        Expr::Conditional {
+            cond,
+            then,
+            otherwise,
+        } => cases(cond, then, otherwise),
+        Expr::Custom { op, args } => {
+            let symbol = mo(&escape(&op.symbol));
+            match (op.fixity, args.as_slice()) {
+                (Fixity::Prefix, [operand]) => {
+                    prefix(symbol, op.precedence, operand)
+                },
+                (Fixity::Postfix, [operand]) => {
+                    mrow(&[group(operand, !is_simple(operand)), symbol])
+                },
+                (Fixity::Infix, [lhs, rhs]) => {
+                    let right = op.associativity == Associativity::Right;
+                    infix(symbol, op.precedence, right, lhs, rhs)
+                },
+                _ => call(&op.symbol, args),
+            }
+        },
+    }
+}
+
+// The character each builtin operator is set with.
+fn operator(operator: Operator) -> &'static str {
+    match operator {
+        Operator::Add => "+",
+        Operator::Substract => "\u{2212}",
+        Operator::Multiply => "\u{b7}",
+        Operator::Divide => "/",
+        Operator::Remainder => "%",
+        Operator::Modulo => "mod",
+        Operator::FloorDivide => "//",
+        Operator::Exponentiate => "^",
+        Operator::IsGreaterThan => "&gt;",
+        Operator::IsLessThan => "&lt;",
+        Operator::IsGreaterThanOrEqualTo => "\u{2265}",
+        Operator::IsLessThanOrEqualTo => "\u{2264}",
+        Operator::IsEqualTo => "=",
+        Operator::IsNotEqualTo => "\u{2260}",
+        Operator::Not => "\u{ac}",
+        Operator::Factorial => "!",
+        Operator::DoubleFactorial => "!!",
+        Operator::Percent => "%",
+        Operator::And => "\u{2227}",
+        Operator::Or => "\u{2228}",
+        Operator::Xor => "\u{22bb}",
+        Operator::Conditional => "?",
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn mo(text: &str) -> String {
+    format!("<mo>{}</mo>", text)
+}
+
+fn mi(text: &str) -> String {
+    format!("<mi>{}</mi>", escape(text))
+}
+
+fn mrow(children: &[String]) -> String {
+    format!("<mrow>{}</mrow>", children.concat())
+}
+
+fn fenced(open: &str, content: String, close: &str) -> String {
+    mrow(&[mo(open), content, mo(close)])
+}
+
+fn group(expr: &Expr, parens: bool) -> String {
+    if parens {
+        fenced("(", element(expr), ")")
+    }
+    else {
+        element(expr)
+    }
+}
+
+fn number(n: NumericLiteral) -> String {
+    if n.is_nan() {
+        mi("NaN")
+    }
+    else if n.is_infinite() && n < 0.0 {
+        mrow(&[mo(operator(Operator::Substract)), mi("\u{221e}")])
+    }
+    else if n.is_infinite() {
+        mi("\u{221e}")
+    }
+    else if n < 0.0 {
+        mrow(&[mo(operator(Operator::Substract)), number(-n)])
+    }
+    else if let Some((mantissa, exponent)) = scientific(n) {
+        let power = format!(
+            "<msup><mn>10</mn>{}</msup>",
+            number(exponent.parse().unwrap_or_default())
+        );
+        let times = mo("\u{d7}");
+        mrow(&[format!("<mn>{}</mn>", mantissa), times, power])
+    }
+    else {
+        format!("<mn>{}</mn>", n)
+    }
+}
+
+fn variable(name: &str) -> String {
+    match name {
+        "PI" => mi("\u{3c0}"),
+        "E" => mi("e"),
+        "INFINITY" => number(f64::INFINITY),
+        "NEG_INFINITY" => number(f64::NEG_INFINITY),
+        _ => identifier(&canonical_name(name)),
+    }
+}
+
+// `x_1` is set as `<msub><mi>x</mi><mn>1</mn></msub>`.
+fn identifier(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_digit()) {
+        return format!("<mn>{}</mn>", name);
+    }
+
+    match name.split_once('_') {
+        Some((base, subscript))
+            if !base.is_empty() && !subscript.is_empty() =>
+        {
+            format!(
+                "<msub>{}{}</msub>",
+                identifier(base),
+                identifier(subscript)
+            )
+        },
+        _ => match greek_glyph(name) {
+            Some(glyph) => mi(&glyph.to_string()),
+            None => mi(name),
+        },
+    }
+}
+
+fn arguments(args: &[Expr]) -> String {
+    let mut children = Vec::with_capacity(args.len() * 2);
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            children.push(mo(","));
+        }
+        children.push(element(arg));
+    }
+    fenced("(", mrow(&children), ")")
+}
+
+fn call(name: &str, args: &[Expr]) -> String {
+    match (name, args) {
+        ("sqrt", [x]) => format!("<msqrt>{}</msqrt>", element(x)),
+        ("cbrt", [x]) => format!("<mroot>{}<mn>3</mn></mroot>", element(x)),
+        ("abs", [x]) => fenced("|", element(x), "|"),
+        ("floor", [x]) => fenced("\u{230a}", element(x), "\u{230b}"),
+        ("ceil", [x]) => fenced("\u{2308}", element(x), "\u{2309}"),
+        ("log", [base, _]) => {
+            let log = format!("<msub>{}{}</msub>", mi("log"), element(base));
+            mrow(&[log, APPLY.to_string(), arguments(&args[1..])])
+        },
+        ("log" | "log_10", [_]) => {
+            let log = format!("<msub>{}<mn>10</mn></msub>", mi("log"));
+            mrow(&[log, APPLY.to_string(), arguments(args)])
+        },
+        ("if", [cond, then, otherwise]) => cases(cond, then, otherwise),
+        _ => mrow(&[mi(name), APPLY.to_string(), arguments(args)]),
+    }
+}
+
+fn cases(cond: &Expr, then: &Expr, otherwise: &Expr) -> String {
+    let row = |value: &Expr, when: String| {
+        format!(
+            "<mtr><mtd>{}</mtd><mtd>{}</mtd></mtr>",
+            element(value),
+            when
+        )
+    };
+    let when = mrow(&["<mtext>if&#xa0;</mtext>".to_string(), element(cond)]);
+    let table = format!(
+        "<mtable>{}{}</mtable>",
+        row(then, when),
+        row(otherwise, "<mtext>otherwise</mtext>".to_string())
+    );
+    mrow(&[mo("{"), table])
+}
+
+// Like the parser, a prefix operator takes everything binding tighter than
+// itself.
+fn prefix(symbol: String, precedence: i8, operand: &Expr) -> String {
+    let parens = level(operand) <= precedence && !is_prefix(operand);
+    mrow(&[symbol, group(operand, parens)])
+}
+
+fn binary(op: BinaryOp, lhs: &Expr, rhs: &Expr) -> String {
+    let builtin = op.operator();
+    match op {
+        BinaryOp::Div => {
+            format!("<mfrac>{}{}</mfrac>", element(lhs), element(rhs))
+        },
+        BinaryOp::FloorDiv => {
+            let fraction =
+                format!("<mfrac>{}{}</mfrac>", element(lhs), element(rhs));
+            fenced("\u{230a}", fraction, "\u{230b}")
+        },
+        BinaryOp::Pow => format!(
+            "<msup>{}{}</msup>",
+            group(lhs, !is_simple(lhs)),
+            element(rhs)
+        ),
+        BinaryOp::Mul if juxtaposed(lhs, rhs) => {
+            mrow(&[element(lhs), TIMES.to_string(), element(rhs)])
+        },
+        _ => infix(
+            mo(operator(builtin)),
+            builtin.get_precedence(),
+            builtin.is_right_associative(),
+            lhs,
+            rhs,
+        ),
+    }
+}
+
+fn infix(
+    symbol: String,
+    precedence: i8,
+    right_associative: bool,
+    lhs: &Expr,
+    rhs: &Expr,
+) -> String {
+    let lhs_parens = level(lhs) < precedence
+        || (right_associative && level(lhs) == precedence);
+    let rhs_parens = negative(rhs)
+        || level(rhs) < precedence
+        || (!right_associative && level(rhs) == precedence);
+
+    mrow(&[group(lhs, lhs_parens), symbol, group(rhs, rhs_parens)])
+}
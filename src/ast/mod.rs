@@ -1,11 +1,20 @@
 mod eval;
+mod expr;
+mod latex;
+mod mathml;
+mod names;
 mod node;
+mod print;
 mod resolve;
 mod scope;
+mod visit;
 
 pub use self::{
     eval::{Evaluate, EvaluationResult, NumericLiteral},
-    node::{Args, Node, Root},
-    resolve::{resolve_fn, resolve_var},
-    scope::{Scope, Variable},
+    expr::{BinaryOp, Expr, SeriesOp, UnaryOp},
+    names::{is_known_fn, is_known_var, Declarations, Names},
+    node::{Args, CompiledExpr, Node, Root},
+    resolve::{is_lazy_fn, resolve_fn, resolve_var},
+    scope::{Bound, Lookup, Scope, Variable},
+    visit::{walk_expr, walk_expr_mut, Visitor, VisitorMut},
 };
@@ -0,0 +1,86 @@
+use crate::{
+    ast::{Scope, Variable},
+    constants::{canonical_name, CONSTANTS, FUNCTIONS},
+};
+use std::collections::HashSet;
+
+/// The variable and function names known while compiling.
+///
+/// Names decide how an expression is split into identifiers: `xy` is a
+/// single variable if `xy` is known and `x * y` otherwise, and `f(x)` is only
+/// a call if `f` is a function. Builtin constants and functions are always
+/// known.
+///
+/// A `Scope` knows the names it holds. `Declarations` lists names without
+/// values, for compiling before any values exist.
+pub trait Names {
+    fn is_variable(&self, name: &str) -> bool;
+
+    fn is_function(&self, name: &str) -> bool;
+}
+
+impl Names for Scope {
+    fn is_variable(&self, name: &str) -> bool {
+        matches!(self.get_var(name), Some(Variable::Number(_)))
+    }
+
+    fn is_function(&self, name: &str) -> bool {
+        matches!(self.get_var(name), Some(Variable::Function(_)))
+    }
+}
+
+/// A set of variable and function names declared ahead of evaluation.
+///
+/// ```
+/// use asciimath::{compile_declared, scope, Declarations, Evaluate};
+///
+/// let names = Declarations::new().variable("xy").function("f");
+/// let expr = compile_declared("2xy + f(1)", &names).unwrap();
+///
+/// let f: asciimath::CustomFn = |args| Ok(args[0] * 10.0);
+/// let scope = scope! { "xy" => 3, "f" => f };
+/// assert_eq!(Ok(16.0), expr.eval_with(&scope));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Declarations {
+    variables: HashSet<String>,
+    functions: HashSet<String>,
+}
+
+impl Declarations {
+    pub fn new() -> Self {
+        Declarations::default()
+    }
+
+    /// Declares a variable. Greek letters may be spelled out or written as
+    /// glyphs, as with `Scope`.
+    pub fn variable(mut self, name: &str) -> Self {
+        self.variables.insert(canonical_name(name).into_owned());
+        self
+    }
+
+    pub fn function(mut self, name: &str) -> Self {
+        self.functions.insert(canonical_name(name).into_owned());
+        self
+    }
+}
+
+impl Names for Declarations {
+    fn is_variable(&self, name: &str) -> bool {
+        self.variables.contains(canonical_name(name).as_ref())
+    }
+
+    fn is_function(&self, name: &str) -> bool {
+        self.functions.contains(canonical_name(name).as_ref())
+    }
+}
+
+/// Whether `name` is a builtin constant or a variable in `names`.
+pub fn is_known_var(name: &str, names: &dyn Names) -> bool {
+    CONSTANTS.contains_key(name) || names.is_variable(name)
+}
+
+/// Whether `name` is a builtin function or a function in `names`.
+pub fn is_known_fn(name: &str, names: &dyn Names) -> bool {
+    FUNCTIONS.contains_key(name) || names.is_function(name)
+}
@@ -1,24 +1,117 @@
-use crate::{ast::Scope, tokens::Token};
-use std::{collections::VecDeque, fmt};
+use crate::{
+    ast::{Names, Scope},
+    error::Error,
+    span::Span,
+    tokens::Token,
+};
+use std::{collections::VecDeque, fmt, ops::Deref};
 
 pub type Args = VecDeque<Node>;
 
+#[derive(Clone)]
 pub struct Node {
     pub token: Token,
     pub args: Option<Args>,
+    pub span: Span,
+}
+
+// Spans only locate errors, so `1+2` and `1 + 2` are the same tree
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.token == other.token && self.args == other.args
+    }
 }
 
 impl Node {
-    pub(crate) fn new(token: Token, args: Option<Args>) -> Self {
-        Self { token, args }
+    pub(crate) fn new(token: Token, args: Option<Args>, span: Span) -> Self {
+        Self { token, args, span }
     }
 }
 
+/// A compiled expression that owns its tree and can be evaluated with any
+/// `Scope`.
+///
+/// Unlike `Root`, it borrows nothing, so it can be cloned, cached or shared
+/// between threads.
+///
+/// Two expressions are equal when their trees are, whatever the spacing of
+/// their source.
+///
+/// ```
+/// use asciimath::{compile, scope, Evaluate};
+/// use std::thread;
+///
+/// let expr = compile("x^2", &scope! {}).unwrap().into_compiled();
+///
+/// let handle = thread::spawn({
+///     let expr = expr.clone();
+///     move || expr.eval_with(&scope! { "x" => 3 })
+/// });
+/// assert_eq!(Ok(9.0), handle.join().unwrap());
+/// assert_eq!(Ok(16.0), expr.eval_with(&scope! { "x" => 4 }));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompiledExpr {
+    pub(crate) node: Node,
+}
+
+/// A compiled expression along with the `Scope` it was compiled with, which
+/// `Evaluate::eval` uses.
 pub struct Root<'a> {
-    pub node: Node,
+    pub expr: CompiledExpr,
     pub scope: &'a Scope,
 }
 
+impl Root<'_> {
+    /// The parsed tree of the expression.
+    pub fn node(&self) -> &Node {
+        &self.expr.node
+    }
+
+    /// Drops the scope, keeping the owned expression.
+    pub fn into_compiled(self) -> CompiledExpr {
+        self.expr
+    }
+}
+
+impl Deref for Root<'_> {
+    type Target = CompiledExpr;
+
+    fn deref(&self) -> &CompiledExpr {
+        &self.expr
+    }
+}
+
+impl CompiledExpr {
+    /// Checks that every variable and function in the expression is either a
+    /// builtin or in `names`, typically the `Scope` it will be evaluated
+    /// with. Each unresolved name is reported as an
+    /// `Error::UnknownVariable` or `Error::UnknownFunction`, in order.
+    ///
+    /// ```
+    /// use asciimath::{compile_declared, scope, Declarations, Error, Span};
+    ///
+    /// let names = Declarations::new().variable("rate").variable("hours");
+    /// let expr = compile_declared("rate * hours", &names).unwrap();
+    ///
+    /// let hours = Error::UnknownVariable("hours".to_string(), Span::new(7, 12));
+    /// assert_eq!(Err(vec![hours]), expr.validate(&scope! { "rate" => 10 }));
+    ///
+    /// let scope = scope! { "rate" => 10, "hours" => 8 };
+    /// assert_eq!(Ok(()), expr.validate(&scope));
+    /// ```
+    pub fn validate(&self, names: &dyn Names) -> Result<(), Vec<Error>> {
+        let mut errors = Vec::new();
+        self.node.unresolved(names, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        }
+        else {
+            Err(errors)
+        }
+    }
+}
+
 impl fmt::Debug for Node {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.token {
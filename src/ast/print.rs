@@ -0,0 +1,342 @@
+use crate::{
+    ast::{BinaryOp, CompiledExpr, Expr, NumericLiteral, Root, UnaryOp},
+    operators::{Associativity, Fixity},
+    options::PrintOptions,
+    tokens::Operator,
+};
+use std::fmt;
+
+// Numbers, variables and calls never need parentheses.
+pub(super) const ATOM: i8 = i8::MAX;
+
+// The operand of a unary minus takes the operators binding at least as
+// tightly as `^`, after which the negation is an operand like any other. It
+// thus needs parentheses before `^` and postfix operators only.
+const NEGATION: i8 = 69;
+
+impl Expr {
+    /// Prints the expression as text that parses back to the same tree,
+    /// with as few parentheses as possible. `Display` uses the default
+    /// `PrintOptions`.
+    ///
+    /// Implicit multiplications are printed assuming the default
+    /// `ParserOptions::implicit_binds_tighter`. Parts of the tree that failed
+    /// to parse are printed as `?`, and negative numbers with a minus, so
+    /// that they parse back as the negation of a positive number. A series
+    /// is printed as `sum(i, from, to, body)`, which the plain syntax reads
+    /// as a call instead.
+    pub fn to_string_with(&self, options: &PrintOptions) -> String {
+        Printer { options }.print(self)
+    }
+}
+
+impl CompiledExpr {
+    /// See `Expr::to_string_with`.
+    pub fn to_string_with(&self, options: &PrintOptions) -> String {
+        self.to_expr().to_string_with(options)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_string_with(&PrintOptions::default()))
+    }
+}
+
+impl fmt::Display for CompiledExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_expr().fmt(f)
+    }
+}
+
+impl fmt::Display for Root<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.expr.fmt(f)
+    }
+}
+
+struct Printer<'o> {
+    options: &'o PrintOptions,
+}
+
+impl Printer<'_> {
+    fn print(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Num(n) => number(*n),
+            Expr::Var(name) => name.clone(),
+            Expr::Error(_) => "?".to_string(),
+            Expr::Call { name, args } => self.call(name, args),
+            Expr::Series {
+                op,
+                index,
+                from,
+                to,
+                body,
+            } => {
+                let index = Expr::Var(index.clone());
+                self.call(op.name(), [&index, from, to, body])
+            },
+            Expr::Unary {
+                op: UnaryOp::Neg,
+                operand,
+            } => {
+                let parens = binding(operand)
+                    < Operator::Exponentiate.get_precedence()
+                    || is_negation(operand);
+                format!("-{}", self.operand(operand, parens))
+            },
+            Expr::Unary { op, operand } => {
+                let operator = op.operator();
+                let symbol = operator.to_string();
+                if op.is_postfix() {
+                    self.postfix(&symbol, operator.get_precedence(), operand)
+                }
+                else {
+                    self.prefix(&symbol, operator.get_precedence(), operand)
+                }
+            },
+            Expr::Binary { op, lhs, rhs } => self.binary(*op, lhs, rhs),
+            Expr::Conditional {
+                cond,
+                then,
+                otherwise,
+            } => {
+                let min = Operator::Conditional.get_precedence();
+                let cond = self.operand(cond, binding(cond) <= min);
+                let (question, colon) = if self.options.spaced {
+                    (" ? ", " : ")
+                }
+                else {
+                    ("?", ":")
+                };
+                format!(
+                    "{}{}{}{}{}",
+                    cond,
+                    question,
+                    self.print(then),
+                    colon,
+                    self.print(otherwise)
+                )
+            },
+            Expr::Custom { op, args } => match (op.fixity, args.as_slice()) {
+                (Fixity::Prefix, [operand]) => {
+                    self.prefix(&op.symbol, op.precedence, operand)
+                },
+                (Fixity::Postfix, [operand]) => {
+                    self.postfix(&op.symbol, op.precedence, operand)
+                },
+                (Fixity::Infix, [lhs, rhs]) => {
+                    let right = op.associativity == Associativity::Right;
+                    let (lhs, rhs) =
+                        self.operands(op.precedence, right, lhs, rhs);
+                    self.join(&lhs, &op.symbol, &rhs)
+                },
+                _ => self.call(&op.symbol, args),
+            },
+        }
+    }
+
+    fn operand(&self, expr: &Expr, parens: bool) -> String {
+        if parens {
+            format!("({})", self.print(expr))
+        }
+        else {
+            self.print(expr)
+        }
+    }
+
+    fn call<'e>(
+        &self,
+        name: &str,
+        args: impl IntoIterator<Item = &'e Expr>,
+    ) -> String {
+        let comma = if self.options.spaced { ", " } else { "," };
+        let args: Vec<String> =
+            args.into_iter().map(|arg| self.print(arg)).collect();
+        format!("{}({})", name, args.join(comma))
+    }
+
+    // The parser reads the operand of a prefix operator up to the operators
+    // binding looser than it, and another prefix operator always starts an
+    // operand.
+    fn prefix(&self, symbol: &str, precedence: i8, operand: &Expr) -> String {
+        let parens = binding(operand) <= precedence && !is_prefix(operand);
+        let operand = self.operand(operand, parens);
+        if is_word(symbol) {
+            format!("{} {}", symbol, operand)
+        }
+        else {
+            format!("{}{}", symbol, operand)
+        }
+    }
+
+    fn postfix(&self, symbol: &str, precedence: i8, operand: &Expr) -> String {
+        let text = self.print(operand);
+        // `x!` followed by `!` would read as `x!!`
+        let merges = text.ends_with('!') && symbol.starts_with('!');
+        let text = if binding(operand) < precedence || merges {
+            format!("({})", text)
+        }
+        else {
+            text
+        };
+
+        if is_word(symbol) {
+            format!("{} {}", text, symbol)
+        }
+        else {
+            format!("{}{}", text, symbol)
+        }
+    }
+
+    fn binary(&self, op: BinaryOp, lhs: &Expr, rhs: &Expr) -> String {
+        let operator = op.operator();
+        let (left, mut right) = self.operands(
+            operator.get_precedence(),
+            operator.is_right_associative(),
+            lhs,
+            rhs,
+        );
+
+        // `%` before anything but an operand is a percentage
+        if op == BinaryOp::Rem && !right.starts_with(starts_operand) {
+            right = format!("({})", right);
+        }
+
+        if op == BinaryOp::Mul
+            && self.options.implicit_multiplication
+            && implicit(lhs, &left, &right)
+        {
+            format!("{}{}", left, right)
+        }
+        else if op == BinaryOp::Pow {
+            format!("{}^{}", left, right)
+        }
+        else {
+            self.join(&left, &operator.to_string(), &right)
+        }
+    }
+
+    fn operands(
+        &self,
+        precedence: i8,
+        right_associative: bool,
+        lhs: &Expr,
+        rhs: &Expr,
+    ) -> (String, String) {
+        let lhs_parens = binding(lhs) < precedence
+            || (right_associative && binding(lhs) == precedence);
+        // A unary minus is an operand on its own, unless followed by
+        // operators binding tighter than `^`
+        let negation = is_negation(rhs)
+            && precedence <= Operator::Exponentiate.get_precedence();
+        let rhs_parens = !negation
+            && (binding(rhs) < precedence
+                || (!right_associative && binding(rhs) == precedence));
+
+        (self.operand(lhs, lhs_parens), self.operand(rhs, rhs_parens))
+    }
+
+    fn join(&self, lhs: &str, symbol: &str, rhs: &str) -> String {
+        // `x% - 1` would otherwise read as the remainder of `x` and `-1`
+        if lhs.ends_with('%') && symbol == "-" {
+            return self.join(&format!("({})", lhs), symbol, rhs);
+        }
+        // `x! ==` would otherwise read as `x != =`
+        let merges = lhs.ends_with('!') && symbol.starts_with(['!', '=']);
+        if self.options.spaced || is_word(symbol) || merges {
+            format!("{} {} {}", lhs, symbol, rhs)
+        }
+        else {
+            format!("{}{}{}", lhs, symbol, rhs)
+        }
+    }
+}
+
+fn number(n: NumericLiteral) -> String {
+    if n.is_infinite() {
+        let sign = if n < 0.0 { "-" } else { "" };
+        format!("{}INFINITY", sign)
+    }
+    else if let Some((mantissa, exponent)) = scientific(n) {
+        format!("{}e{}", mantissa, exponent)
+    }
+    else {
+        n.to_string()
+    }
+}
+
+/// The mantissa and exponent of a finite number far from 1, which written
+/// out would take up to 300 zeros as in `1e300`.
+pub(super) fn scientific(n: NumericLiteral) -> Option<(String, String)> {
+    if !n.is_finite() || n == 0.0 || (1e-6..1e16).contains(&n.abs()) {
+        return None;
+    }
+    let text = format!("{:e}", n);
+    let (mantissa, exponent) = text.split_once('e')?;
+    Some((mantissa.to_string(), exponent.to_string()))
+}
+
+pub(super) fn binding(expr: &Expr) -> i8 {
+    match expr {
+        _ if is_negation(expr) => NEGATION,
+        Expr::Num(_)
+        | Expr::Var(_)
+        | Expr::Call { .. }
+        | Expr::Series { .. }
+        | Expr::Error(_) => ATOM,
+        Expr::Unary { op, .. } => op.operator().get_precedence(),
+        Expr::Binary { op, .. } => op.operator().get_precedence(),
+        Expr::Conditional { .. } => Operator::Conditional.get_precedence(),
+        Expr::Custom { op, .. } => op.precedence,
+    }
+}
+
+// Whether the expression is printed starting with a unary minus.
+pub(super) fn is_negation(expr: &Expr) -> bool {
+    match expr {
+        Expr::Num(n) => n.is_sign_negative(),
+        Expr::Unary { op, .. } => *op == UnaryOp::Neg,
+        _ => false,
+    }
+}
+
+pub(super) fn is_prefix(expr: &Expr) -> bool {
+    match expr {
+        Expr::Unary { op, .. } => !op.is_postfix(),
+        Expr::Custom { op, .. } => op.fixity == Fixity::Prefix,
+        _ => false,
+    }
+}
+
+fn is_word(symbol: &str) -> bool {
+    symbol.starts_with(char::is_alphabetic)
+}
+
+fn starts_operand(c: char) -> bool {
+    c.is_alphanumeric() || c == '(' || c == '_' || c == '.'
+}
+
+// Whether `lhs rhs` lexes as an implicit multiplication of the two: a number
+// before a name or a parenthesis, or a parenthesis before either.
+fn implicit(lhs: &Expr, left: &str, right: &str) -> bool {
+    let number = match lhs {
+        Expr::Num(n) => *n > 0.0,
+        Expr::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => matches!(**operand, Expr::Num(n) if n > 0.0),
+        _ => false,
+    };
+    // A letter after a number could continue it, as in `2e5`
+    let name = right
+        .starts_with(|c: char| c.is_alphabetic() && !matches!(c, 'e' | 'E'));
+
+    (number && number_text(left) && (name || right.starts_with('(')))
+        || (left.ends_with(')')
+            && right.starts_with(|c: char| c == '(' || c.is_alphabetic()))
+}
+
+fn number_text(text: &str) -> bool {
+    text.ends_with(|c: char| c.is_ascii_digit())
+}
@@ -1,25 +1,97 @@
 use crate::{
-    ast::{NumericLiteral, Scope, Variable},
-    constants::{Func, CONSTANTS, FUNCTIONS},
+    ast::{
+        is_known_fn, is_known_var, Lookup, Names, Node, NumericLiteral,
+        Variable,
+    },
+    constants::{Func, CONSTANTS, FUNCTIONS, LAZY_FUNCTIONS},
     error::Error,
+    tokens::Token,
 };
 
-pub fn resolve_fn<'a>(name: &str, scope: &'a Scope) -> Result<&'a Func, Error> {
-    FUNCTIONS.get(name).map_or_else(
-        || match scope.get_var(name) {
-            Some(Variable::Function(f)) => Ok(f),
-            _ => Err(Error::UnknownFunction(name.to_string())),
-        },
-        |f| Ok(f),
-    )
+pub fn resolve_fn<'a>(
+    name: &str,
+    scope: &'a dyn Lookup,
+) -> Option<&'a Func> {
+    FUNCTIONS.get(name).or_else(|| match scope.lookup(name) {
+        Some(Variable::Function(f)) => Some(f),
+        _ => None,
+    })
 }
 
-pub fn resolve_var(name: &str, scope: &Scope) -> Result<NumericLiteral, Error> {
-    CONSTANTS.get(name).map_or_else(
-        || match scope.get_var(name) {
-            Some(Variable::Number(n)) => Ok(*n),
-            _ => Err(Error::UnknownVariable(name.to_string())),
-        },
-        |f| Ok(*f),
-    )
+pub fn resolve_var(
+    name: &str,
+    scope: &dyn Lookup,
+) -> Option<NumericLiteral> {
+    CONSTANTS
+        .get(name)
+        .copied()
+        .or_else(|| match scope.lookup(name) {
+            Some(Variable::Number(n)) => Some(*n),
+            _ => None,
+        })
+}
+
+/// Whether `name` is a builtin that evaluates its arguments on demand, such
+/// as `if`.
+pub fn is_lazy_fn(name: &str) -> bool {
+    LAZY_FUNCTIONS.contains(&name)
+}
+
+impl Node {
+    /// Collects an error for every variable or function that is neither a
+    /// builtin nor in `names`.
+    pub(crate) fn unresolved(
+        &self,
+        names: &dyn Names,
+        errors: &mut Vec<Error>,
+    ) {
+        match self.token {
+            Token::Variable(ref var) if !is_known_var(var, names) => {
+                errors.push(Error::UnknownVariable(var.clone(), self.span))
+            },
+            Token::Function(ref f)
+                if !is_known_fn(f, names) && !is_lazy_fn(f) =>
+            {
+                errors.push(Error::UnknownFunction(f.clone(), self.span))
+            },
+            _ => {},
+        }
+
+        match self.series_index() {
+            // The index is bound within the body, the last argument
+            Some(index) => {
+                let args = self.args.iter().flatten().enumerate().skip(1);
+                for (i, arg) in args {
+                    if i < 3 {
+                        arg.unresolved(names, errors);
+                        continue;
+                    }
+                    let mut found = Vec::new();
+                    arg.unresolved(names, &mut found);
+                    errors.extend(found.into_iter().filter(|err| match err {
+                        Error::UnknownVariable(var, _) => var != index,
+                        _ => true,
+                    }));
+                }
+            },
+            None => {
+                for arg in self.args.iter().flatten() {
+                    arg.unresolved(names, errors);
+                }
+            },
+        }
+    }
+
+    // The index variable of a sum or product.
+    fn series_index(&self) -> Option<&str> {
+        match (&self.token, &self.args) {
+            (Token::Series(_), Some(args)) => {
+                match args.front().map(|index| &index.token) {
+                    Some(Token::Variable(index)) => Some(index),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
 }
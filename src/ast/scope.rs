@@ -1,6 +1,10 @@
-use crate::{ast::NumericLiteral, constants::CustomFn};
+use crate::{
+    ast::NumericLiteral,
+    constants::{canonical_name, CustomFn},
+};
 use std::{collections::HashMap, convert::Into};
 
+#[derive(Clone)]
 pub enum Variable {
     Number(NumericLiteral),
     Function(CustomFn),
@@ -25,7 +29,7 @@ impl From<CustomFn> for Variable {
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Scope {
     variables: HashMap<String, Variable>,
 }
@@ -43,11 +47,50 @@ impl Scope {
         }
     }
 
+    /// Sets a variable or function. A Greek letter may be spelled out or
+    /// written as a glyph: `alpha` and `α` name the same variable.
     pub fn set_var<T: Into<Variable>>(&mut self, var_name: &str, value: T) {
-        self.variables.insert(var_name.to_string(), value.into());
+        let name = canonical_name(var_name).into_owned();
+        self.variables.insert(name, value.into());
     }
 
     pub fn get_var(&self, var_name: &str) -> Option<&Variable> {
-        self.variables.get(var_name)
+        self.variables.get(canonical_name(var_name).as_ref())
+    }
+
+    pub(crate) fn variables(&self) -> impl Iterator<Item = (&str, &Variable)> {
+        self.variables
+            .iter()
+            .map(|(name, var)| (name.as_str(), var))
+    }
+}
+
+/// Where evaluation looks up variables and functions.
+pub trait Lookup {
+    fn lookup(&self, name: &str) -> Option<&Variable>;
+}
+
+impl Lookup for Scope {
+    fn lookup(&self, name: &str) -> Option<&Variable> {
+        self.get_var(name)
+    }
+}
+
+/// A variable bound on top of another lookup, such as the index of a sum,
+/// which shadows a variable of the same name without copying the scope.
+pub struct Bound<'a> {
+    pub name: &'a str,
+    pub value: Variable,
+    pub parent: &'a dyn Lookup,
+}
+
+impl Lookup for Bound<'_> {
+    fn lookup(&self, name: &str) -> Option<&Variable> {
+        if canonical_name(name) == canonical_name(self.name) {
+            Some(&self.value)
+        }
+        else {
+            self.parent.lookup(name)
+        }
     }
 }
@@ -0,0 +1,105 @@
+use crate::ast::{Expr, NumericLiteral};
+
+/// Walks an `Expr` tree.
+///
+/// Every method has a default implementation, so a visitor only overrides
+/// the nodes it cares about. `visit_expr` is called for every node and by
+/// default dispatches to the other methods through `walk_expr`, which also
+/// visits the children.
+///
+/// ```
+/// use asciimath::{compile, scope, Visitor};
+///
+/// struct Variables(Vec<String>);
+///
+/// impl Visitor for Variables {
+///     fn visit_var(&mut self, name: &str) {
+///         self.0.push(name.to_string());
+///     }
+/// }
+///
+/// let expr = compile("a + max(b, 2c)", &scope! {}).unwrap().to_expr();
+/// let mut variables = Variables(Vec::new());
+/// variables.visit_expr(&expr);
+///
+/// assert_eq!(variables.0, ["a", "b", "c"]);
+/// ```
+pub trait Visitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_num(&mut self, _value: NumericLiteral) {}
+
+    fn visit_var(&mut self, _name: &str) {}
+
+    /// Called before the arguments of a function call are visited.
+    fn visit_call(&mut self, _name: &str, _args: &[Expr]) {}
+}
+
+/// Visits `expr` with `visitor`: numbers, variables and calls through their
+/// own method, then every child through `visit_expr`.
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Num(value) => visitor.visit_num(*value),
+        Expr::Var(name) => visitor.visit_var(name),
+        Expr::Call { name, args } => visitor.visit_call(name, args),
+        _ => {},
+    }
+    for child in expr.children() {
+        visitor.visit_expr(child);
+    }
+}
+
+/// Walks an `Expr` tree, allowing it to be rewritten in place.
+///
+/// ```
+/// use asciimath::{
+///     compile_declared, scope, CompiledExpr, Declarations, Evaluate, Expr,
+///     VisitorMut,
+/// };
+///
+/// // Replaces `rate` with its value
+/// struct Inline(f64);
+///
+/// impl VisitorMut for Inline {
+///     fn visit_expr_mut(&mut self, expr: &mut Expr) {
+///         if *expr == Expr::Var("rate".to_string()) {
+///             *expr = Expr::Num(self.0);
+///         }
+///         asciimath::walk_expr_mut(self, expr);
+///     }
+/// }
+///
+/// let names = Declarations::new().variable("rate").variable("hours");
+/// let mut expr = compile_declared("rate * hours", &names).unwrap().to_expr();
+/// Inline(10.0).visit_expr_mut(&mut expr);
+///
+/// let expr = CompiledExpr::from(expr);
+/// assert_eq!(Ok(80.0), expr.eval_with(&scope! { "hours" => 8 }));
+/// ```
+pub trait VisitorMut {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_num_mut(&mut self, _value: &mut NumericLiteral) {}
+
+    fn visit_var_mut(&mut self, _name: &mut String) {}
+
+    /// Called before the arguments of a function call are visited.
+    fn visit_call_mut(&mut self, _name: &mut String, _args: &mut Vec<Expr>) {}
+}
+
+/// See `walk_expr`.
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::Num(value) => visitor.visit_num_mut(value),
+        Expr::Var(name) => visitor.visit_var_mut(name),
+        Expr::Call { name, args } => visitor.visit_call_mut(name, args),
+        _ => {},
+    }
+    for child in expr.children_mut() {
+        visitor.visit_expr_mut(child);
+    }
+}
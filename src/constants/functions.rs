@@ -1,4 +1,8 @@
-use crate::ast::{EvaluationResult, NumericLiteral};
+use crate::{
+    ast::{EvaluationResult, NumericLiteral},
+    error::Error,
+    span::Span,
+};
 use once_cell::sync::Lazy;
 use std::{collections::HashMap, f64};
 
@@ -6,6 +10,10 @@ pub type Args = Vec<NumericLiteral>;
 pub type Func = fn(&Args) -> EvaluationResult;
 pub type CustomFn = Func;
 
+/// Builtins that are evaluated by the tree itself, since only some of their
+/// arguments may be evaluated.
+pub const LAZY_FUNCTIONS: &[&str] = &["if"];
+
 pub static FUNCTIONS: Lazy<HashMap<&'static str, Func>> = Lazy::new(|| {
     let mut m = HashMap::with_capacity(15);
 
@@ -28,7 +36,16 @@ pub static FUNCTIONS: Lazy<HashMap<&'static str, Func>> = Lazy::new(|| {
     let sqrt = |args: &Args| Ok(args[0].sqrt());
     let cbrt = |args: &Args| Ok(args[0].cbrt());
 
-    let log = |args: &Args| Ok(args[1].log(args[0]));
+    // Without a base, `log` is the common logarithm. The span of the error is
+    // filled in by the caller
+    let log = |args: &Args| match args[..] {
+        [x] => Ok(x.log10()),
+        [base, x, ..] => Ok(x.log(base)),
+        _ => Err(Error::NotEnoughFunctionParams(
+            "log".to_string(),
+            Span::default(),
+        )),
+    };
     let log_10 = |args: &Args| Ok(args[0].log(10.0));
     let ln = |args: &Args| Ok(args[0].ln());
 
@@ -0,0 +1,76 @@
+use std::borrow::Cow;
+
+/// Greek letters usable as variable names, spelled out or as a glyph. `π`
+/// is left out since it is the constant `PI`.
+const GREEK_LETTERS: &[(&str, char)] = &[
+    ("alpha", 'α'),
+    ("beta", 'β'),
+    ("gamma", 'γ'),
+    ("delta", 'δ'),
+    ("epsilon", 'ε'),
+    ("zeta", 'ζ'),
+    ("eta", 'η'),
+    ("theta", 'θ'),
+    ("iota", 'ι'),
+    ("kappa", 'κ'),
+    ("lambda", 'λ'),
+    ("mu", 'μ'),
+    ("nu", 'ν'),
+    ("xi", 'ξ'),
+    ("omicron", 'ο'),
+    ("rho", 'ρ'),
+    ("sigma", 'σ'),
+    ("tau", 'τ'),
+    ("upsilon", 'υ'),
+    ("phi", 'φ'),
+    ("chi", 'χ'),
+    ("psi", 'ψ'),
+    ("omega", 'ω'),
+    ("Gamma", 'Γ'),
+    ("Delta", 'Δ'),
+    ("Theta", 'Θ'),
+    ("Lambda", 'Λ'),
+    ("Xi", 'Ξ'),
+    ("Sigma", 'Σ'),
+    ("Upsilon", 'Υ'),
+    ("Phi", 'Φ'),
+    ("Psi", 'Ψ'),
+    ("Omega", 'Ω'),
+];
+
+/// Whether `name` is a spelled-out Greek letter such as `theta`.
+pub fn is_greek_name(name: &str) -> bool {
+    GREEK_LETTERS.iter().any(|&(spelled, _)| spelled == name)
+}
+
+/// The name a variable is stored under, with Greek glyphs spelled out so
+/// that `θ_0` and `theta_0` are the same variable.
+pub fn canonical_name(name: &str) -> Cow<'_, str> {
+    if !name.chars().any(|c| greek_name(c).is_some()) {
+        return Cow::Borrowed(name);
+    }
+
+    let mut canonical = String::with_capacity(name.len() * 2);
+    for c in name.chars() {
+        match greek_name(c) {
+            Some(spelled) => canonical.push_str(spelled),
+            None => canonical.push(c),
+        }
+    }
+    Cow::Owned(canonical)
+}
+
+/// The glyph of a spelled-out Greek letter, `θ` for `theta`.
+pub fn greek_glyph(name: &str) -> Option<char> {
+    GREEK_LETTERS
+        .iter()
+        .find(|&&(spelled, _)| spelled == name)
+        .map(|&(_, glyph)| glyph)
+}
+
+fn greek_name(glyph: char) -> Option<&'static str> {
+    GREEK_LETTERS
+        .iter()
+        .find(|&&(_, c)| c == glyph)
+        .map(|&(spelled, _)| spelled)
+}
@@ -1,5 +1,9 @@
 mod functions;
-pub use self::functions::{Args, CustomFn, Func, FUNCTIONS};
+mod greek;
+pub use self::{
+    functions::{CustomFn, Func, FUNCTIONS, LAZY_FUNCTIONS},
+    greek::{canonical_name, greek_glyph, is_greek_name},
+};
 
 use crate::ast::NumericLiteral;
 use once_cell::sync::Lazy;
@@ -0,0 +1,218 @@
+use crate::{
+    ast::{Scope, Variable},
+    constants::{CONSTANTS, FUNCTIONS},
+    error::Error,
+    span::Span,
+    util::edit_distance,
+};
+use std::fmt;
+
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Renders an `Error` against the expression it came from, underlining the
+/// offending part of the input.
+///
+/// ```
+/// use asciimath::{eval, scope, Diagnostic};
+///
+/// let expr = "2 * sqr(16)";
+/// let scope = scope! {};
+/// let err = eval(expr, &scope).unwrap_err();
+///
+/// println!("{}", Diagnostic::new(&err, expr).with_suggestions(&scope));
+/// ```
+pub struct Diagnostic<'a> {
+    error: &'a Error,
+    source: &'a str,
+    notes: Vec<String>,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn new(error: &'a Error, source: &'a str) -> Self {
+        Diagnostic {
+            error,
+            source,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Appends a free-form note below the underlined source.
+    pub fn with_note<T: Into<String>>(mut self, note: T) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Adds a "did you mean" note for unknown variables and functions, based
+    /// on the names in `scope` and the builtins.
+    pub fn with_suggestions(mut self, scope: &Scope) -> Self {
+        let suggestions = self.suggestions(scope);
+        match suggestions.as_slice() {
+            [] => {},
+            [name] => self.notes.push(format!("did you mean `{}`?", name)),
+            names => self.notes.push(format!(
+                "did you mean one of {}?",
+                names
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+        self
+    }
+
+    pub fn error(&self) -> &Error {
+        self.error
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    /// The error with unknown names widened to the whole identifier in the
+    /// source, which the lexer may have split into single letters.
+    fn located(&self) -> Error {
+        let span = match *self.error {
+            Error::UnknownVariable(_, span)
+            | Error::UnknownFunction(_, span) => span,
+            ref err => return err.clone(),
+        };
+        let word = word_at(self.source, Span::new(span.start, span.start));
+        if word.is_empty() {
+            return self.error.clone();
+        }
+
+        let name = self.source[word.start..word.end].to_string();
+        match self.error {
+            Error::UnknownFunction(..) => Error::UnknownFunction(name, word),
+            _ => Error::UnknownVariable(name, word),
+        }
+    }
+
+    /// Closest known names to the identifier the error points at.
+    pub fn suggestions(&self, scope: &Scope) -> Vec<String> {
+        let (name, is_call) = match self.located() {
+            Error::UnknownVariable(name, span) => {
+                let rest = self.source[span.end..].trim_start();
+                (name, rest.starts_with('('))
+            },
+            Error::UnknownFunction(name, _) => (name, true),
+            _ => return Vec::new(),
+        };
+
+        let candidates: Vec<&str> = if is_call {
+            FUNCTIONS
+                .keys()
+                .copied()
+                .chain(scope.variables().filter_map(|(name, var)| match var {
+                    Variable::Function(_) => Some(name),
+                    _ => None,
+                }))
+                .collect()
+        }
+        else {
+            CONSTANTS
+                .keys()
+                .copied()
+                .chain(scope.variables().filter_map(|(name, var)| match var {
+                    Variable::Number(_) => Some(name),
+                    _ => None,
+                }))
+                .collect()
+        };
+
+        closest(&name, candidates)
+    }
+}
+
+fn closest(name: &str, candidates: Vec<&str>) -> Vec<String> {
+    let threshold = name.chars().count().div_ceil(3);
+    let mut scored: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect();
+
+    scored.sort();
+    scored.dedup();
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Moves `span` within `source` and onto char boundaries, in case it comes
+/// from another expression.
+fn clamp(source: &str, span: Span) -> Span {
+    let floor = |i: usize| {
+        (0..=i.min(source.len()))
+            .rev()
+            .find(|&i| source.is_char_boundary(i))
+            .unwrap_or(0)
+    };
+    let start = floor(span.start);
+    Span::new(start, floor(span.end).max(start))
+}
+
+/// Expands `span` to the identifier surrounding it.
+fn word_at(source: &str, span: Span) -> Span {
+    let span = clamp(source, span);
+    let start = source[..span.start]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_word_char(c))
+        .last()
+        .map_or(span.start, |(i, _)| i);
+    let end = source[span.end..]
+        .char_indices()
+        .find(|&(_, c)| !is_word_char(c))
+        .map_or(source.len(), |(i, _)| span.end + i);
+
+    Span::new(start, end)
+}
+
+impl<'a> fmt::Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let error = self.located();
+        // An operator definition has no place in the source to underline
+        if !error.is_located() {
+            write!(f, "error: {}", error)?;
+            for note in &self.notes {
+                write!(f, "\n  = {}", note)?;
+            }
+            return Ok(());
+        }
+
+        let span = clamp(self.source, error.span());
+        let start = span.start;
+        let line_start = self.source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.source[start..]
+            .find('\n')
+            .map_or(self.source.len(), |i| start + i);
+        let line = &self.source[line_start..line_end];
+        let line_number = self.source[..line_start].matches('\n').count() + 1;
+
+        // Columns are counted in chars so the carets line up with the source.
+        let end = span.end.clamp(start, line_end);
+        let column = self.source[line_start..start].chars().count();
+        let width = self.source[start..end].chars().count().max(1);
+
+        let gutter = line_number.to_string();
+        let pad = " ".repeat(gutter.len());
+
+        writeln!(f, "error: {}", error)?;
+        writeln!(f, "{} |", pad)?;
+        writeln!(f, "{} | {}", gutter, line)?;
+        write!(f, "{} | {}{}", pad, " ".repeat(column), "^".repeat(width))?;
+        for note in &self.notes {
+            write!(f, "\n{} = {}", pad, note)?;
+        }
+        Ok(())
+    }
+}
@@ -1,62 +1,154 @@
+use crate::span::Span;
 use std::fmt;
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub enum Error {
-    MissingOperands(String),         // operator
-    NotEnoughFunctionParams(String), // fn name
-    FunctionSyntaxError(String),     // fn name
-    UnknownFunction(String),         // fn name
-    UnknownVariable(String),         // var name
-    CannotEvaluateToken(String),     // invalid token on stack
-    InvalidToken(String),
-    EmptyExpression,
+    MissingOperands(String, Span),         // operator
+    NotEnoughFunctionParams(String, Span), // fn name
+    FunctionSyntaxError(String, Span),     // fn name
+    UnknownFunction(String, Span),         // fn name
+    UnknownVariable(String, Span),         // var name
+    CannotEvaluateToken(String, Span),     // invalid token on stack
+    InvalidToken(String, Span),
+    UnexpectedToken(String, Span),
+    UnsupportedSyntax(String, Span), // construct as written
+    UnbalancedParentheses(Span),
+    IncompleteConditional(Span),
+    ImplicitMultiplication(Span),
+    DivisionByZero(Span),
+    EmptyExpression(Span),
+    // Definitions rejected by `ParserBuilder::build`, with no expression
+    InvalidOperatorSymbol(String), // operator
+    AmbiguousOperator(String),     // operator
+    OperatorPrecedence(String),    // operator
+    CustomOperatorSyntax(String),  // operator
+}
+
+impl Error {
+    /// The byte range of the expression this error refers to. Errors about
+    /// an operator definition refer to no expression, and have an empty span
+    /// at the start.
+    pub fn span(&self) -> Span {
+        match *self {
+            Error::MissingOperands(_, span)
+            | Error::NotEnoughFunctionParams(_, span)
+            | Error::FunctionSyntaxError(_, span)
+            | Error::UnknownFunction(_, span)
+            | Error::UnknownVariable(_, span)
+            | Error::CannotEvaluateToken(_, span)
+            | Error::InvalidToken(_, span)
+            | Error::UnexpectedToken(_, span)
+            | Error::UnsupportedSyntax(_, span)
+            | Error::UnbalancedParentheses(span)
+            | Error::IncompleteConditional(span)
+            | Error::ImplicitMultiplication(span)
+            | Error::DivisionByZero(span)
+            | Error::EmptyExpression(span) => span,
+            Error::InvalidOperatorSymbol(_)
+            | Error::AmbiguousOperator(_)
+            | Error::OperatorPrecedence(_)
+            | Error::CustomOperatorSyntax(_) => Span::default(),
+        }
+    }
+
+    /// Whether the error points into an expression, which is not the case
+    /// for the errors of `ParserBuilder::build`.
+    pub(crate) fn is_located(&self) -> bool {
+        !matches!(
+            self,
+            Error::InvalidOperatorSymbol(_)
+                | Error::AmbiguousOperator(_)
+                | Error::OperatorPrecedence(_)
+                | Error::CustomOperatorSyntax(_)
+        )
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::MissingOperands(operator) => write!(
+            Error::MissingOperands(operator, _) => write!(
                 f,
                 "Expected more operands for operator \"{}\"",
                 operator
             ),
-            Error::NotEnoughFunctionParams(fn_name) => write!(
+            Error::NotEnoughFunctionParams(fn_name, _) => write!(
                 f,
                 "Expected more parameters for function \"{}\"",
                 fn_name
             ),
-            Error::FunctionSyntaxError(fn_name) => write!(
+            Error::FunctionSyntaxError(fn_name, _) => write!(
                 f,
                 "Syntax error in function \"{}()\". Usually this occurs \
                  because of missing or extra commas.",
                 fn_name
             ),
-            Error::UnknownFunction(fn_name) => write!(
+            Error::UnknownFunction(fn_name, _) => write!(
                 f,
                 "Function\"{}()\" does not exist. If it is a custom function, \
                  make sure you are passing it through the Scope.",
                 fn_name
             ),
-            Error::UnknownVariable(var_name) => write!(
+            Error::UnknownVariable(var_name, _) => write!(
                 f,
                 "Variable \"{}\" is not defined. Make sure you are passing it \
                  through the Scope.",
                 var_name
             ),
-            Error::CannotEvaluateToken(token) => write!(
+            Error::CannotEvaluateToken(token, _) => write!(
                 f,
                 "Token \"{}\" does not belong on the stack. Please open an \
                  issue with your expression at \
                  https://github.com/chmln/asciimath-rs/issues",
                 token
             ),
-            Error::InvalidToken(token) => {
+            Error::InvalidToken(token, _) => {
                 write!(f, "Invalid token: \"{}\"", token)
             },
-            Error::EmptyExpression => write!(
+            Error::UnexpectedToken(token, _) => {
+                write!(f, "Unexpected \"{}\"", token)
+            },
+            Error::UnsupportedSyntax(construct, _) => write!(
+                f,
+                "\"{}\" has no numeric value and cannot be evaluated",
+                construct
+            ),
+            Error::UnbalancedParentheses(_) => {
+                write!(f, "Unbalanced parentheses")
+            },
+            Error::IncompleteConditional(_) => {
+                write!(f, "Expected \":\" to complete the conditional")
+            },
+            Error::ImplicitMultiplication(_) => write!(
+                f,
+                "Missing operator. Implicit multiplication is disabled, write \
+                 \"*\" instead."
+            ),
+            Error::DivisionByZero(_) => write!(f, "Division by zero"),
+            Error::EmptyExpression(_) => write!(
                 f,
                 "The expression is empty and there is nothing to evaluate"
             ),
+            Error::InvalidOperatorSymbol(operator) => write!(
+                f,
+                "Operator \"{}\" must be a word or made of punctuation",
+                operator
+            ),
+            Error::AmbiguousOperator(operator) => write!(
+                f,
+                "Operator \"{}\" cannot be both infix and postfix",
+                operator
+            ),
+            Error::OperatorPrecedence(operator) => write!(
+                f,
+                "Operator \"{}\" must have a precedence below i8::MAX",
+                operator
+            ),
+            Error::CustomOperatorSyntax(operator) => write!(
+                f,
+                "Custom operator \"{}\" can only be read with Syntax::Plain",
+                operator
+            ),
         }
     }
 }
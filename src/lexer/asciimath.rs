@@ -0,0 +1,841 @@
+// The AsciiMath front-end, lowering AsciiMath markup to the tokens of the
+// equivalent plain expression.
+//
+// Markup is first scanned into symbols, then read with the grammar of
+// asciimath.org: an expression is a sequence of intermediate expressions,
+// fractions and operators, and an intermediate expression is a simple one
+// with an optional exponent. Constructs the plain syntax spells differently,
+// such as `frac(a)(b)`, `root(n)(x)` or `sum_(i=1)^n`, are written out as
+// the tokens of a parenthesized plain expression.
+
+use super::{
+    bounds, divide, ends_operand, is_identifier_char,
+    number::{consume_number, starts_number},
+    parenthesized, push_term, unicode_constant, unicode_root,
+};
+use crate::{
+    ast::{Names, SeriesOp},
+    constants::{is_greek_name, FUNCTIONS},
+    error::Error,
+    span::Span,
+    tokens::{Operator, SpannedToken, Token, TokenList},
+};
+use std::{iter::Peekable, vec};
+
+// Words that only make sense as presentation, as opposed to words such as
+// `sin` that have a value. They are rejected rather than read as a product
+// of single letters.
+const UNSUPPORTED: &[&str] = &[
+    "text",
+    "mbox",
+    "bb",
+    "bbb",
+    "cc",
+    "tt",
+    "fr",
+    "sf",
+    "hat",
+    "bar",
+    "ul",
+    "vec",
+    "dot",
+    "ddot",
+    "tilde",
+    "overset",
+    "underset",
+    "stackrel",
+    "color",
+    "cancel",
+    "obrace",
+    "ubrace",
+    "overbrace",
+    "underbrace",
+    "int",
+    "oint",
+    "lim",
+    "Lim",
+    "del",
+    "grad",
+    "partial",
+    "nabla",
+    "in",
+    "notin",
+    "sub",
+    "sup",
+    "sube",
+    "supe",
+    "uu",
+    "nn",
+    "uuu",
+    "nnn",
+    "AA",
+    "EE",
+    "CC",
+    "NN",
+    "QQ",
+    "RR",
+    "ZZ",
+    "to",
+    "rarr",
+    "larr",
+    "harr",
+    "rArr",
+    "lArr",
+    "hArr",
+    "implies",
+    "iff",
+    "prop",
+    "aleph",
+    "quad",
+    "qquad",
+    "cdots",
+    "vdots",
+    "ddots",
+    "ldots",
+    "approx",
+    "equiv",
+    "cong",
+    "sim",
+    "prec",
+    "succ",
+    "langle",
+    "rangle",
+    "angle",
+    "forall",
+    "exists",
+    "emptyset",
+];
+
+// Functions of AsciiMath that are not builtins. They are read as calls, for
+// the user to define.
+const FUNCTION_WORDS: &[&str] = &[
+    "sec", "csc", "cot", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "sech", "csch", "coth", "exp", "det", "dim", "gcd", "lcm",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Symbol {
+    Number(Token),
+    Name(String),
+    Constant(&'static str),
+    Operator(Operator),
+    Minus,
+    // `!`, a factorial after an operand and a negation otherwise
+    Bang,
+    Percent,
+    Slash,
+    Caret,
+    Underscore,
+    Comma,
+    Open(Bracket),
+    Close(Bracket),
+    Bar,
+    Function(String),
+    Frac,
+    Root,
+    // `sum` or `prod`
+    Series(SeriesOp),
+    Unsupported,
+    Invalid,
+}
+
+// Each kind of bracket only closes one opened the same way, so `(x]` is
+// unbalanced
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bracket {
+    Paren,
+    Square,
+    Brace,
+    // `(:` and `:)`
+    Angle,
+    // `{:` and `:}`
+    Invisible,
+    Floor,
+    Ceil,
+}
+
+impl Symbol {
+    // Whether the symbol begins a simple expression.
+    fn starts_simple(&self) -> bool {
+        !matches!(
+            self,
+            Symbol::Operator(_)
+                | Symbol::Bang
+                | Symbol::Percent
+                | Symbol::Slash
+                | Symbol::Caret
+                | Symbol::Underscore
+                | Symbol::Comma
+                | Symbol::Close(_)
+        )
+    }
+}
+
+/// Tokenizes AsciiMath markup into the tokens of the equivalent plain
+/// expression, leaving a `Token::Error` in place of anything unsupported.
+pub fn tokenize(expr: &str, names: &dyn Names, strict: bool) -> TokenList {
+    let symbols = scan(expr, names, strict);
+    let mut reader = Reader {
+        expr,
+        symbols: symbols.into_iter().peekable(),
+        end: Span::new(expr.len(), expr.len()),
+    };
+
+    let mut tokens = Vec::with_capacity(expr.len());
+    loop {
+        reader.expression(&mut tokens, false);
+        // A closing bracket without an opening one is left to the parser
+        match reader.next() {
+            Some((_, span)) => {
+                tokens.push(SpannedToken::new(Token::RightParenthesis, span))
+            },
+            None => break,
+        }
+    }
+    tokens
+}
+
+fn scan(expr: &str, names: &dyn Names, strict: bool) -> Vec<(Symbol, Span)> {
+    let mut chars = expr.char_indices().peekable();
+    let mut symbols = Vec::new();
+
+    while let Some(&(start, ch)) = chars.peek() {
+        // `_` is always a subscript, never a digit separator
+        if starts_number(&chars) {
+            let end = expr[start..].find('_').map_or(expr.len(), |i| start + i);
+            let mut digits = expr[start..end].char_indices().peekable();
+            let number = consume_number(&mut digits, start);
+            while chars.peek().is_some_and(|&(i, _)| i < number.span.end) {
+                chars.next();
+            }
+            symbols.push((Symbol::Number(number.token), number.span));
+            continue;
+        }
+
+        let rest = &expr[start..];
+        let (len, symbol) = if ch.is_ascii_alphabetic() {
+            let letters = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            word(&rest[..letters], names)
+        }
+        else if let Some((len, symbol)) = punctuation(rest) {
+            (len, symbol)
+        }
+        else if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        else if let Some(name) = unicode_constant(ch) {
+            (ch.len_utf8(), Symbol::Constant(name))
+        }
+        else if let Some(name) = unicode_root(ch) {
+            (ch.len_utf8(), Symbol::Function(name.into()))
+        }
+        else if ch.is_alphabetic() {
+            (ch.len_utf8(), Symbol::Name(ch.to_string()))
+        }
+        else if strict {
+            (ch.len_utf8(), Symbol::Invalid)
+        }
+        else {
+            chars.next();
+            continue;
+        };
+
+        while chars.peek().is_some_and(|&(i, _)| i < start + len) {
+            chars.next();
+        }
+        symbols.push((symbol, Span::new(start, start + len)));
+    }
+    symbols
+}
+
+// Reads the longest known symbol at the start of a run of letters. A run
+// without one starts with a single-letter variable.
+fn word(letters: &str, names: &dyn Names) -> (usize, Symbol) {
+    (1..=letters.len())
+        .rev()
+        .find_map(|len| known_word(&letters[..len], names).map(|s| (len, s)))
+        .unwrap_or_else(|| (1, Symbol::Name(letters[..1].to_string())))
+}
+
+fn known_word(word: &str, names: &dyn Names) -> Option<Symbol> {
+    let symbol = match word {
+        _ if names.is_variable(word) => Symbol::Name(word.into()),
+        _ if names.is_function(word) => Symbol::Function(word.into()),
+        "pi" => Symbol::Constant("PI"),
+        "e" => Symbol::Constant("E"),
+        "oo" => Symbol::Constant("INFINITY"),
+        "xx" | "cdot" | "ast" => Symbol::Operator(Operator::Multiply),
+        "div" => Symbol::Operator(Operator::Divide),
+        "mod" => Symbol::Operator(Operator::Modulo),
+        "lt" => Symbol::Operator(Operator::IsLessThan),
+        "gt" => Symbol::Operator(Operator::IsGreaterThan),
+        "le" | "leq" => Symbol::Operator(Operator::IsLessThanOrEqualTo),
+        "ge" | "geq" => Symbol::Operator(Operator::IsGreaterThanOrEqualTo),
+        "ne" => Symbol::Operator(Operator::IsNotEqualTo),
+        "and" | "wedge" => Symbol::Operator(Operator::And),
+        "or" | "vv" | "vee" => Symbol::Operator(Operator::Or),
+        "not" | "neg" => Symbol::Operator(Operator::Not),
+        "lfloor" => Symbol::Open(Bracket::Floor),
+        "lceil" => Symbol::Open(Bracket::Ceil),
+        "rfloor" => Symbol::Close(Bracket::Floor),
+        "rceil" => Symbol::Close(Bracket::Ceil),
+        "frac" => Symbol::Frac,
+        "root" => Symbol::Root,
+        "sum" => Symbol::Series(SeriesOp::Sum),
+        "prod" => Symbol::Series(SeriesOp::Prod),
+        "log" => Symbol::Function("log".into()),
+        // The norm of a number is its absolute value
+        "norm" => Symbol::Function("abs".into()),
+        _ if is_greek_name(word) => Symbol::Name(word.into()),
+        _ if FUNCTIONS.contains_key(word) || FUNCTION_WORDS.contains(&word) => {
+            Symbol::Function(word.into())
+        },
+        _ if UNSUPPORTED.contains(&word) => Symbol::Unsupported,
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+fn punctuation(rest: &str) -> Option<(usize, Symbol)> {
+    // Longer symbols come first, as `|__` before `|`
+    const SYMBOLS: &[(&str, Symbol)] = &[
+        ("<=>", Symbol::Unsupported),
+        ("|->", Symbol::Unsupported),
+        ("|__", Symbol::Open(Bracket::Floor)),
+        ("__|", Symbol::Close(Bracket::Floor)),
+        ("|~", Symbol::Open(Bracket::Ceil)),
+        ("~|", Symbol::Close(Bracket::Ceil)),
+        ("(:", Symbol::Open(Bracket::Angle)),
+        (":)", Symbol::Close(Bracket::Angle)),
+        ("{:", Symbol::Open(Bracket::Invisible)),
+        (":}", Symbol::Close(Bracket::Invisible)),
+        ("<=", Symbol::Operator(Operator::IsLessThanOrEqualTo)),
+        (">=", Symbol::Operator(Operator::IsGreaterThanOrEqualTo)),
+        ("!=", Symbol::Operator(Operator::IsNotEqualTo)),
+        ("-:", Symbol::Operator(Operator::Divide)),
+        ("//", Symbol::Operator(Operator::Divide)),
+        ("^^", Symbol::Operator(Operator::And)),
+        ("**", Symbol::Operator(Operator::Multiply)),
+        ("->", Symbol::Unsupported),
+        ("=>", Symbol::Unsupported),
+        ("+-", Symbol::Unsupported),
+        ("-+", Symbol::Unsupported),
+        (":=", Symbol::Unsupported),
+        ("-=", Symbol::Unsupported),
+        ("~~", Symbol::Unsupported),
+        ("...", Symbol::Unsupported),
+        ("+", Symbol::Operator(Operator::Add)),
+        ("-", Symbol::Minus),
+        ("−", Symbol::Minus),
+        ("*", Symbol::Operator(Operator::Multiply)),
+        ("×", Symbol::Operator(Operator::Multiply)),
+        ("·", Symbol::Operator(Operator::Multiply)),
+        ("÷", Symbol::Operator(Operator::Divide)),
+        ("/", Symbol::Slash),
+        ("^", Symbol::Caret),
+        ("_", Symbol::Underscore),
+        (",", Symbol::Comma),
+        ("=", Symbol::Operator(Operator::IsEqualTo)),
+        ("<", Symbol::Operator(Operator::IsLessThan)),
+        (">", Symbol::Operator(Operator::IsGreaterThan)),
+        ("≤", Symbol::Operator(Operator::IsLessThanOrEqualTo)),
+        ("≥", Symbol::Operator(Operator::IsGreaterThanOrEqualTo)),
+        ("≠", Symbol::Operator(Operator::IsNotEqualTo)),
+        ("¬", Symbol::Operator(Operator::Not)),
+        ("∧", Symbol::Operator(Operator::And)),
+        ("∨", Symbol::Operator(Operator::Or)),
+        ("!", Symbol::Bang),
+        ("%", Symbol::Percent),
+        ("(", Symbol::Open(Bracket::Paren)),
+        ("[", Symbol::Open(Bracket::Square)),
+        ("{", Symbol::Open(Bracket::Brace)),
+        (")", Symbol::Close(Bracket::Paren)),
+        ("]", Symbol::Close(Bracket::Square)),
+        ("}", Symbol::Close(Bracket::Brace)),
+        ("|", Symbol::Bar),
+    ];
+
+    // Quoted text runs up to the closing quote
+    if let Some(text) = rest.strip_prefix('"') {
+        let len = text.find('"').map_or(rest.len(), |end| end + 2);
+        return Some((len, Symbol::Unsupported));
+    }
+    SYMBOLS
+        .iter()
+        .find(|(text, _)| rest.starts_with(text))
+        .map(|(text, symbol)| (text.len(), symbol.clone()))
+}
+
+struct Reader<'e> {
+    expr: &'e str,
+    symbols: Peekable<vec::IntoIter<(Symbol, Span)>>,
+    end: Span,
+}
+
+impl<'e> Reader<'e> {
+    fn peek(&mut self) -> Option<&Symbol> {
+        self.symbols.peek().map(|(symbol, _)| symbol)
+    }
+
+    fn peek_span(&mut self) -> Span {
+        let end = self.end;
+        self.symbols.peek().map_or(end, |&(_, span)| span)
+    }
+
+    fn next(&mut self) -> Option<(Symbol, Span)> {
+        self.symbols.next()
+    }
+
+    fn next_if(&mut self, symbol: &Symbol) -> Option<Span> {
+        match self.symbols.peek() {
+            Some((next, span)) if next == symbol => {
+                let span = *span;
+                self.symbols.next();
+                Some(span)
+            },
+            _ => None,
+        }
+    }
+
+    // Reads up to a closing bracket, or up to a `|` closing an absolute
+    // value when `bar` is set.
+    fn expression(&mut self, tokens: &mut TokenList, bar: bool) {
+        loop {
+            let span = self.peek_span();
+            let token = match self.peek() {
+                None | Some(Symbol::Close(_)) => return,
+                Some(Symbol::Bar) if bar && ends_operand(tokens) => return,
+                Some(Symbol::Minus) if ends_operand(tokens) => {
+                    Token::Operator(Operator::Substract)
+                },
+                Some(Symbol::Minus) => {
+                    tokens.push(SpannedToken::new(Token::Number(-1.0), span));
+                    Token::Operator(Operator::Multiply)
+                },
+                Some(Symbol::Operator(operator)) => Token::Operator(*operator),
+                Some(Symbol::Bang) => Token::Operator(Operator::Not),
+                Some(Symbol::Percent) => Token::Operator(Operator::Remainder),
+                Some(Symbol::Slash) => Token::Operator(Operator::Divide),
+                Some(Symbol::Caret) => Token::Operator(Operator::Exponentiate),
+                Some(Symbol::Comma) => Token::Comma,
+                Some(Symbol::Underscore) => {
+                    let span = self.subscript_span();
+                    tokens.push(self.unsupported_token(span));
+                    continue;
+                },
+                Some(_) => {
+                    let term = self.fraction();
+                    push_term(tokens, term);
+                    continue;
+                },
+            };
+            self.next();
+            tokens.push(SpannedToken::new(token, span));
+        }
+    }
+
+    // An intermediate expression, divided by the ones after each `/`.
+    fn fraction(&mut self) -> TokenList {
+        let mut term = self.intermediate();
+        while let Some(slash) = self.next_if(&Symbol::Slash) {
+            let denominator = self.operand("/", slash, Self::intermediate);
+            term = divide(term, slash, denominator);
+        }
+        term
+    }
+
+    // A power followed by postfix operators.
+    fn intermediate(&mut self) -> TokenList {
+        let mut term = self.power();
+        loop {
+            let span = self.peek_span();
+            let adjacent = term.last().is_some_and(|last| {
+                last.token == Token::Operator(Operator::Factorial)
+                    && last.span.end == span.start
+            });
+            let remainder = self.operand_follows();
+            match self.peek() {
+                Some(Symbol::Bang) if adjacent => {
+                    let last = term.pop().unwrap();
+                    let double = Token::Operator(Operator::DoubleFactorial);
+                    term.push(SpannedToken::new(double, last.span.to(span)));
+                },
+                Some(Symbol::Bang) => {
+                    let factorial = Token::Operator(Operator::Factorial);
+                    term.push(SpannedToken::new(factorial, span));
+                },
+                Some(Symbol::Percent) if !remainder => {
+                    let percent = Token::Operator(Operator::Percent);
+                    term.push(SpannedToken::new(percent, span));
+                },
+                Some(Symbol::Underscore) => {
+                    let span = self.subscript_span();
+                    term.push(self.unsupported_token(span));
+                    continue;
+                },
+                _ => return term,
+            }
+            self.next();
+        }
+    }
+
+    // Whether the symbol after the next one begins an operand, so that a
+    // `%` is a remainder rather than a percentage. A `-` before the operand
+    // negates it, as in `10 % -3`.
+    fn operand_follows(&self) -> bool {
+        let mut rest = self.symbols.clone().skip(1).map(|(symbol, _)| symbol);
+        match rest.next() {
+            Some(Symbol::Minus) => rest.next().is_some_and(|symbol| {
+                symbol.starts_simple() && symbol != Symbol::Minus
+            }),
+            next => next.is_some_and(|symbol| symbol.starts_simple()),
+        }
+    }
+
+    // A simple expression with its exponents.
+    fn power(&mut self) -> TokenList {
+        let mut term = self.simple();
+        while let Some(caret) = self.next_if(&Symbol::Caret) {
+            let exponent = self.operand("^", caret, Self::simple);
+            let power = Token::Operator(Operator::Exponentiate);
+            term.push(SpannedToken::new(power, caret));
+            term.extend(exponent);
+        }
+        term
+    }
+
+    // Reads an operand of `owner`, or reports it missing.
+    fn operand(
+        &mut self,
+        owner: &str,
+        span: Span,
+        read: fn(&mut Self) -> TokenList,
+    ) -> TokenList {
+        if self.peek().is_some_and(Symbol::starts_simple) {
+            read(self)
+        }
+        else {
+            let err = Error::MissingOperands(owner.to_string(), span);
+            vec![SpannedToken::new(Token::Error(err), span)]
+        }
+    }
+
+    fn simple(&mut self) -> TokenList {
+        let (symbol, span) = match self.next() {
+            Some(next) => next,
+            None => return Vec::new(),
+        };
+        let token = |token| vec![SpannedToken::new(token, span)];
+
+        match symbol {
+            Symbol::Number(number) => token(number),
+            Symbol::Constant(name) => token(Token::Variable(name.into())),
+            Symbol::Name(name) => self.identifier(name, span),
+            Symbol::Minus => {
+                let mut negation = token(Token::Number(-1.0));
+                negation.push(SpannedToken::new(
+                    Token::Operator(Operator::Multiply),
+                    span,
+                ));
+                negation.extend(self.operand("-", span, Self::simple));
+                negation
+            },
+            Symbol::Open(bracket) => self.group(bracket, span),
+            Symbol::Bar => {
+                let mut tokens = token(Token::Function("abs".into()));
+                tokens.push(SpannedToken::new(Token::LeftParenthesis, span));
+                let mut inner = Vec::new();
+                self.expression(&mut inner, true);
+                tokens.extend(inner);
+                if let Some(bar) = self.next_if(&Symbol::Bar) {
+                    let close = Token::RightParenthesis;
+                    tokens.push(SpannedToken::new(close, bar));
+                }
+                tokens
+            },
+            Symbol::Function(name) => self.function(name, span),
+            Symbol::Frac => {
+                let numerator = self.operand("frac", span, Self::simple);
+                let denominator = self.operand("frac", span, Self::simple);
+                divide(numerator, span, denominator)
+            },
+            Symbol::Root => self.root(span),
+            Symbol::Series(op) => self.series(op, span),
+            Symbol::Unsupported => vec![self.unsupported_token(span)],
+            Symbol::Invalid => {
+                let text = self.expr[span.start..span.end].to_string();
+                token(Token::Error(Error::InvalidToken(text, span)))
+            },
+            // Not reached, `operand` checks for these
+            _ => token(Token::Error(Error::UnexpectedToken(
+                self.expr[span.start..span.end].to_string(),
+                span,
+            ))),
+        }
+    }
+
+    // A bracketed expression. Floor and ceiling brackets call `floor` and
+    // `ceil`.
+    fn group(&mut self, bracket: Bracket, open: Span) -> TokenList {
+        let mut tokens = Vec::new();
+        match bracket {
+            Bracket::Floor => tokens
+                .push(SpannedToken::new(Token::Function("floor".into()), open)),
+            Bracket::Ceil => tokens
+                .push(SpannedToken::new(Token::Function("ceil".into()), open)),
+            _ => {},
+        }
+        tokens.push(SpannedToken::new(Token::LeftParenthesis, open));
+        let mut inner = Vec::new();
+        self.expression(&mut inner, false);
+        tokens.extend(inner);
+        if let Some(&(Symbol::Close(closing), close)) = self.symbols.peek() {
+            self.next();
+            if closing != bracket {
+                let err = Error::UnbalancedParentheses(close);
+                tokens.push(SpannedToken::new(Token::Error(err), close));
+            }
+            tokens.push(SpannedToken::new(Token::RightParenthesis, close));
+        }
+        tokens
+    }
+
+    // A variable, with a subscript of letters and digits as in `x_1` or
+    // `x_(max)` naming the variable `x_1` or `x_max`.
+    fn identifier(&mut self, name: String, span: Span) -> TokenList {
+        let variable =
+            |name, span| vec![SpannedToken::new(Token::Variable(name), span)];
+        if self.peek() != Some(&Symbol::Underscore) {
+            return variable(name, span);
+        }
+
+        let subscript = self.subscript_span();
+        let text = &self.expr[subscript.start..subscript.end];
+        let text = text[1..]
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .trim();
+        // Only subscripts that the plain syntax reads back, so not `x_π`
+        if !text.is_empty() && text.chars().all(is_identifier_char) {
+            variable(format!("{}_{}", name, text), span.to(subscript))
+        }
+        else {
+            vec![self.unsupported_token(span.to(subscript))]
+        }
+    }
+
+    // Consumes a `_` and the simple expression after it, returning their
+    // span.
+    fn subscript_span(&mut self) -> Span {
+        let (_, underscore) = self.next().unwrap();
+        let subscript = self.operand("_", underscore, Self::simple);
+        subscript
+            .last()
+            .map_or(underscore, |last| underscore.to(last.span))
+    }
+
+    // A function applied to the simple expression after it, with its
+    // exponent as in `sin x^2`. Brackets around the argument are its
+    // parentheses, so `max(a, b)` takes two arguments. `log_b x` is the
+    // logarithm of `x` in base `b`, and `sin^2 x` is `sin(x)^2`.
+    fn function(&mut self, name: String, span: Span) -> TokenList {
+        let mut base = None;
+        if name == "log" && self.peek() == Some(&Symbol::Underscore) {
+            let underscore = self.next().unwrap().1;
+            base = Some(self.operand("_", underscore, Self::simple));
+        }
+        let mut exponent = None;
+        if let Some(caret) = self.next_if(&Symbol::Caret) {
+            if self.peek() == Some(&Symbol::Minus) {
+                // `sin^-1 x` is the inverse function
+                let inverse = self.simple();
+                let end = inverse.last().map_or(caret, |last| last.span);
+                return vec![self.unsupported_token(span.to(end))];
+            }
+            exponent = Some((caret, self.operand("^", caret, Self::simple)));
+        }
+
+        let argument = match self.peek() {
+            Some(&Symbol::Open(
+                bracket @ (Bracket::Paren | Bracket::Square | Bracket::Brace),
+            )) => {
+                let open = self.next().unwrap().1;
+                self.group(bracket, open)
+            },
+            Some(next) if next.starts_simple() => {
+                // A radical takes a simple expression, as it is drawn over it
+                let argument = if name == "sqrt" || name == "cbrt" {
+                    self.simple()
+                }
+                else {
+                    self.power()
+                };
+                parenthesized(argument, span)
+            },
+            _ => {
+                let err = Error::NotEnoughFunctionParams(name, span);
+                return vec![SpannedToken::new(Token::Error(err), span)];
+            },
+        };
+
+        let mut tokens = Vec::new();
+        match base {
+            Some(base) => {
+                let (start, _) = bounds(&base, span);
+                let (_, end) = bounds(&argument, span);
+                tokens.push(SpannedToken::new(Token::Function(name), span));
+                tokens.push(SpannedToken::new(Token::LeftParenthesis, start));
+                tokens.extend(base);
+                tokens.push(SpannedToken::new(Token::Comma, start));
+                tokens.extend(argument);
+                tokens.push(SpannedToken::new(Token::RightParenthesis, end));
+            },
+            None => {
+                // A logarithm without a base is a common logarithm
+                let name = if name == "log" { "log_10".into() } else { name };
+                tokens.push(SpannedToken::new(Token::Function(name), span));
+                tokens.extend(argument);
+            },
+        }
+        if let Some((caret, exponent)) = exponent {
+            let power = Token::Operator(Operator::Exponentiate);
+            tokens.push(SpannedToken::new(power, caret));
+            tokens.extend(exponent);
+        }
+        tokens
+    }
+
+    // `root(n)(x)` is `sqrt(x)` or `cbrt(x)` for a literal 2 or 3 and
+    // `x^(1/n)` otherwise.
+    fn root(&mut self, span: Span) -> TokenList {
+        let index = self.operand("root", span, Self::simple);
+        let radicand = self.operand("root", span, Self::simple);
+
+        let literal = index
+            .iter()
+            .filter(|token| token.token != Token::LeftParenthesis)
+            .filter(|token| token.token != Token::RightParenthesis)
+            .map(|token| &token.token)
+            .collect::<Vec<_>>();
+        let function = match literal.as_slice() {
+            [Token::Number(n)] if *n == 2.0 => Some("sqrt"),
+            [Token::Number(n)] if *n == 3.0 => Some("cbrt"),
+            _ => None,
+        };
+        if let Some(function) = function {
+            let mut tokens =
+                vec![SpannedToken::new(Token::Function(function.into()), span)];
+            tokens.extend(parenthesized(radicand, span));
+            return tokens;
+        }
+
+        let (start, end) = bounds(&index, span);
+        let mut exponent = vec![SpannedToken::new(Token::Number(1.0), start)];
+        exponent
+            .push(SpannedToken::new(Token::Operator(Operator::Divide), start));
+        exponent.extend(parenthesized(index, span));
+        let mut tokens = parenthesized(radicand, span);
+        tokens.push(SpannedToken::new(
+            Token::Operator(Operator::Exponentiate),
+            end,
+        ));
+        tokens.extend(parenthesized(exponent, span));
+        tokens
+    }
+
+    // `sum_(i=a)^b body` is a `Token::Series` applied to `(i, a, b, body)`,
+    // where the body runs up to the next explicit operator.
+    fn series(&mut self, op: SeriesOp, span: Span) -> TokenList {
+        let bounds = self.series_bounds(span);
+        let (index, lower, upper) = match bounds {
+            Ok(bounds) => bounds,
+            Err(end) => return vec![self.unsupported_token(span.to(end))],
+        };
+
+        let mut body = Vec::new();
+        while self
+            .peek()
+            .is_some_and(|next| next.starts_simple() && *next != Symbol::Minus)
+        {
+            let term = self.fraction();
+            push_term(&mut body, term);
+        }
+        if body.is_empty() {
+            let err = Error::MissingOperands(op.name().to_string(), span);
+            return vec![SpannedToken::new(Token::Error(err), span)];
+        }
+
+        let end = body
+            .last()
+            .map_or(span, |last| Span::new(last.span.end, last.span.end));
+        let mut tokens = vec![
+            SpannedToken::new(Token::Series(op), span),
+            SpannedToken::new(Token::LeftParenthesis, span),
+            index,
+            SpannedToken::new(Token::Comma, span),
+        ];
+        tokens.extend(lower);
+        tokens.push(SpannedToken::new(Token::Comma, span));
+        tokens.extend(upper);
+        tokens.push(SpannedToken::new(Token::Comma, span));
+        tokens.extend(body);
+        tokens.push(SpannedToken::new(Token::RightParenthesis, end));
+        tokens
+    }
+
+    // Reads `_(i=a)^b`, returning the index and the bounds, or the end of
+    // what was read when it has another form.
+    fn series_bounds(
+        &mut self,
+        span: Span,
+    ) -> Result<(SpannedToken, TokenList, TokenList), Span> {
+        let underscore = self.next_if(&Symbol::Underscore).ok_or(span)?;
+        let mut subscript = self.operand("_", underscore, Self::simple);
+        let end = subscript.last().map_or(underscore, |last| last.span);
+
+        let is_index = matches!(
+            subscript.as_slice(),
+            [
+                SpannedToken {
+                    token: Token::LeftParenthesis,
+                    ..
+                },
+                SpannedToken {
+                    token: Token::Variable(_),
+                    ..
+                },
+                SpannedToken {
+                    token: Token::Operator(Operator::IsEqualTo),
+                    ..
+                },
+                ..,
+                SpannedToken {
+                    token: Token::RightParenthesis,
+                    ..
+                },
+            ]
+        ) && subscript.len() > 4;
+        if !is_index {
+            return Err(end);
+        }
+        subscript.pop();
+        let lower = subscript.split_off(3);
+        let index = subscript.swap_remove(1);
+
+        let caret = self.next_if(&Symbol::Caret).ok_or(end)?;
+        let upper = self.operand("^", caret, Self::simple);
+        Ok((index, lower, upper))
+    }
+
+    fn unsupported_token(&self, span: Span) -> SpannedToken {
+        let text = self.expr[span.start..span.end].to_string();
+        SpannedToken::new(
+            Token::Error(Error::UnsupportedSyntax(text, span)),
+            span,
+        )
+    }
+}
@@ -0,0 +1,935 @@
+// The LaTeX front-end, lowering LaTeX math to the tokens of the equivalent
+// plain expression.
+//
+// Input is first scanned into symbols, one per command, letter or digit
+// group, then read into terms. Operators are passed through to the parser,
+// while commands with arguments, such as `\frac{a}{b}` or `\sqrt[n]{x}`, are
+// written out as the tokens of a parenthesized plain expression.
+
+use super::{
+    bounds, divide, ends_operand, is_identifier_char,
+    number::{consume_number, starts_number, Chars},
+    parenthesized, push_term, unicode_constant,
+};
+use crate::{
+    ast::{Names, SeriesOp},
+    constants::is_greek_name,
+    error::Error,
+    span::Span,
+    tokens::{Operator, SpannedToken, Token, TokenList},
+};
+use std::{iter::Peekable, vec};
+
+// Commands whose braced argument is read as if it were not braced, as in
+// `a \mathbin{\%} b`.
+const TRANSPARENT: &[&str] =
+    &[r"\mathbin", r"\mathrel", r"\mathord", r"\mathop"];
+
+// Commands that only adjust spacing or style.
+const IGNORED: &[&str] = &[
+    r"\,",
+    r"\;",
+    r"\:",
+    r"\!",
+    r"\ ",
+    r"\quad",
+    r"\qquad",
+    r"\displaystyle",
+    r"\textstyle",
+    r"\limits",
+    r"\nolimits",
+];
+
+// Commands spelling a function that is not a builtin. They are read as
+// calls, for the user to define.
+const FUNCTION_COMMANDS: &[&str] = &[
+    "sec", "csc", "cot", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+    "coth", "exp", "det", "gcd", "lg",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Symbol {
+    Number(Token),
+    Name(String),
+    Constant(&'static str),
+    Operator(Operator),
+    Minus,
+    Bang,
+    Percent,
+    Caret,
+    Underscore,
+    Comma,
+    Open(Delimiter),
+    Close,
+    Bar,
+    Left,
+    Right,
+    // `.`, the empty delimiter of `\left.`
+    Dot,
+    Function(String),
+    Frac,
+    Sqrt,
+    // `\sum` or `\prod`
+    Series(SeriesOp),
+    Unsupported,
+    Invalid,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Delimiter {
+    Paren,
+    Bracket,
+    Brace,
+    Floor,
+    Ceil,
+}
+
+impl Symbol {
+    // Whether the symbol begins a term.
+    fn starts_term(&self) -> bool {
+        !matches!(
+            self,
+            Symbol::Operator(_)
+                | Symbol::Bang
+                | Symbol::Percent
+                | Symbol::Caret
+                | Symbol::Underscore
+                | Symbol::Comma
+                | Symbol::Close
+                | Symbol::Right
+        )
+    }
+
+    // Whether the symbol begins a factor of a function argument or of the
+    // body of a sum, which end before a minus or another function.
+    fn continues_product(&self) -> bool {
+        self.starts_term()
+            && !matches!(
+                self,
+                Symbol::Minus | Symbol::Function(_) | Symbol::Series(_)
+            )
+    }
+}
+
+/// Tokenizes LaTeX math into the tokens of the equivalent plain expression,
+/// leaving a `Token::Error` in place of anything unsupported.
+pub fn tokenize(expr: &str, names: &dyn Names, strict: bool) -> TokenList {
+    let symbols = scan(expr, names, strict);
+    let mut reader = Reader {
+        expr,
+        symbols: symbols.into_iter().peekable(),
+        end: Span::new(expr.len(), expr.len()),
+    };
+
+    let mut tokens = Vec::with_capacity(expr.len());
+    loop {
+        reader.expression(&mut tokens, false);
+        // A closing delimiter without an opening one is left to the parser
+        match reader.next() {
+            Some((_, span)) => {
+                tokens.push(SpannedToken::new(Token::RightParenthesis, span))
+            },
+            None => break,
+        }
+    }
+    tokens
+}
+
+fn scan(expr: &str, names: &dyn Names, strict: bool) -> Vec<(Symbol, Span)> {
+    let mut chars = expr.char_indices().peekable();
+    let mut symbols: Vec<(Symbol, Span)> = Vec::new();
+    // Brace depth inside each transparent command
+    let mut transparent = Vec::new();
+    let mut depth = 0;
+    // Arguments still expected by a `^`, `_`, `\frac` or `\sqrt`. Such an
+    // argument is a single digit or letter unless braced.
+    let mut arguments = 0;
+
+    while let Some(&(start, ch)) = chars.peek() {
+        let rest = &expr[start..];
+        let single = arguments > 0;
+
+        let (len, symbol) = if ch.is_ascii_digit() && single {
+            let digit = ch.to_digit(10).unwrap_or_default();
+            (1, Symbol::Number(Token::Number(digit.into())))
+        }
+        // `_` is always a subscript, never a digit separator
+        else if starts_number(&chars) {
+            let end = rest.find('_').map_or(expr.len(), |i| start + i);
+            let mut digits = expr[start..end].char_indices().peekable();
+            let number = consume_number(&mut digits, start);
+            (number.span.end - start, Symbol::Number(number.token))
+        }
+        else if ch == '\\' {
+            let command = command_name(rest);
+            if TRANSPARENT.contains(&command)
+                && rest[command.len()..].trim_start().starts_with('{')
+            {
+                let brace = rest[command.len()..].find('{').unwrap_or(0);
+                depth += 1;
+                transparent.push(depth);
+                skip(&mut chars, start + command.len() + brace + 1);
+                continue;
+            }
+            if IGNORED.contains(&command) {
+                skip(&mut chars, start + command.len());
+                continue;
+            }
+            match text_command(command, &rest[command.len()..]) {
+                Some((len, symbol)) => (command.len() + len, symbol),
+                None => (command.len(), command_symbol(command)),
+            }
+        }
+        else if ch.is_ascii_alphabetic() {
+            let letters = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            if single {
+                (1, letter(&rest[..1], names))
+            }
+            else {
+                word(&rest[..letters], names)
+            }
+        }
+        // A comment runs to the end of the line
+        else if ch == '%' {
+            skip(
+                &mut chars,
+                rest.find('\n').map_or(expr.len(), |i| start + i),
+            );
+            continue;
+        }
+        else if ch.is_whitespace() || ch == '~' {
+            chars.next();
+            continue;
+        }
+        else if ch == '}' && transparent.last() == Some(&depth) {
+            transparent.pop();
+            depth -= 1;
+            chars.next();
+            continue;
+        }
+        else if let Some((len, symbol)) = punctuation(rest) {
+            (len, symbol)
+        }
+        else if let Some(name) = unicode_constant(ch) {
+            (ch.len_utf8(), Symbol::Constant(name))
+        }
+        else if ch.is_alphabetic() {
+            (ch.len_utf8(), Symbol::Name(ch.to_string()))
+        }
+        else if strict {
+            (ch.len_utf8(), Symbol::Invalid)
+        }
+        else {
+            chars.next();
+            continue;
+        };
+
+        match ch {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {},
+        }
+        arguments = match symbol {
+            Symbol::Caret | Symbol::Underscore | Symbol::Sqrt => 1,
+            Symbol::Frac => 2,
+            Symbol::Number(_) | Symbol::Name(_) if single => arguments - 1,
+            _ => 0,
+        };
+
+        skip(&mut chars, start + len);
+        symbols.push((symbol, Span::new(start, start + len)));
+    }
+    symbols
+}
+
+fn skip(chars: &mut Chars, end: usize) {
+    while chars.peek().is_some_and(|&(i, _)| i < end) {
+        chars.next();
+    }
+}
+
+// A backslash followed by letters, or by a single other character as in
+// `\,` or `\%`.
+fn command_name(rest: &str) -> &str {
+    let after = &rest[1..];
+    let len = match after.find(|c: char| !c.is_ascii_alphabetic()) {
+        Some(0) => after.chars().next().map_or(0, char::len_utf8),
+        Some(len) => len,
+        None => after.len(),
+    };
+    &rest[..1 + len]
+}
+
+// Commands taking a name in braces: `\mathrm{rate}` is the variable `rate`
+// and `\operatorname{f}` the function `f`, but `\mathrm{e}` is the constant
+// `E`. Text such as `\text{if}` is prose rather than a name, and is
+// unsupported as a whole. Returns the length of the braced name along with
+// the symbol.
+fn text_command(command: &str, rest: &str) -> Option<(usize, Symbol)> {
+    if !matches!(
+        command,
+        r"\mathrm" | r"\mathit" | r"\text" | r"\textrm" | r"\operatorname"
+    ) {
+        return None;
+    }
+
+    let text = rest.strip_prefix('{')?;
+    let end = text.find('}')?;
+    let name = text[..end].replace(r"\_", "_");
+    let is_name = name.starts_with(|c: char| c.is_alphabetic())
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+    let symbol = match command {
+        _ if !is_name => Symbol::Unsupported,
+        r"\text" | r"\textrm" => Symbol::Unsupported,
+        r"\operatorname" => Symbol::Function(name),
+        r"\mathrm" if name == "e" => Symbol::Constant("E"),
+        _ => Symbol::Name(name),
+    };
+    Some((end + 2, symbol))
+}
+
+fn command_symbol(command: &str) -> Symbol {
+    let name = &command[1..];
+    match name {
+        "frac" | "dfrac" | "tfrac" => Symbol::Frac,
+        "sqrt" => Symbol::Sqrt,
+        "sum" => Symbol::Series(SeriesOp::Sum),
+        "prod" => Symbol::Series(SeriesOp::Prod),
+        "pi" => Symbol::Constant("PI"),
+        "infty" => Symbol::Constant("INFINITY"),
+        "cdot" | "times" | "ast" => Symbol::Operator(Operator::Multiply),
+        "div" => Symbol::Operator(Operator::Divide),
+        "bmod" | "mod" => Symbol::Operator(Operator::Modulo),
+        "lt" => Symbol::Operator(Operator::IsLessThan),
+        "gt" => Symbol::Operator(Operator::IsGreaterThan),
+        "le" | "leq" | "leqslant" => {
+            Symbol::Operator(Operator::IsLessThanOrEqualTo)
+        },
+        "ge" | "geq" | "geqslant" => {
+            Symbol::Operator(Operator::IsGreaterThanOrEqualTo)
+        },
+        "ne" | "neq" => Symbol::Operator(Operator::IsNotEqualTo),
+        "land" | "wedge" => Symbol::Operator(Operator::And),
+        "lor" | "vee" => Symbol::Operator(Operator::Or),
+        "oplus" | "veebar" => Symbol::Operator(Operator::Xor),
+        "neg" | "lnot" => Symbol::Operator(Operator::Not),
+        "%" => Symbol::Percent,
+        "left" => Symbol::Left,
+        "right" => Symbol::Right,
+        "{" | "lbrace" => Symbol::Open(Delimiter::Brace),
+        "}" | "rbrace" => Symbol::Close,
+        "lfloor" => Symbol::Open(Delimiter::Floor),
+        "lceil" => Symbol::Open(Delimiter::Ceil),
+        "rfloor" | "rceil" => Symbol::Close,
+        "vert" | "lvert" | "rvert" => Symbol::Bar,
+        "sin" | "cos" | "tan" | "ln" | "log" | "min" | "max" => {
+            Symbol::Function(name.into())
+        },
+        _ if FUNCTION_COMMANDS.contains(&name) => Symbol::Function(name.into()),
+        _ if is_greek_name(name) => Symbol::Name(name.into()),
+        // `\varepsilon` is another way of drawing `\epsilon`
+        _ if name.strip_prefix("var").is_some_and(is_greek_name) => {
+            Symbol::Name(name["var".len()..].into())
+        },
+        _ => Symbol::Unsupported,
+    }
+}
+
+// Reads the longest declared name at the start of a run of letters. Other
+// letters are single-letter variables, as usual in LaTeX.
+fn word(letters: &str, names: &dyn Names) -> (usize, Symbol) {
+    (2..=letters.len())
+        .rev()
+        .map(|len| &letters[..len])
+        .find(|word| names.is_variable(word) || names.is_function(word))
+        .map(|word| (word.len(), letter(word, names)))
+        .unwrap_or_else(|| (1, letter(&letters[..1], names)))
+}
+
+fn letter(name: &str, names: &dyn Names) -> Symbol {
+    if names.is_function(name) {
+        Symbol::Function(name.into())
+    }
+    else if name == "e" && !names.is_variable(name) {
+        Symbol::Constant("E")
+    }
+    else {
+        Symbol::Name(name.into())
+    }
+}
+
+fn punctuation(rest: &str) -> Option<(usize, Symbol)> {
+    const SYMBOLS: &[(&str, Symbol)] = &[
+        ("+", Symbol::Operator(Operator::Add)),
+        ("-", Symbol::Minus),
+        ("−", Symbol::Minus),
+        ("*", Symbol::Operator(Operator::Multiply)),
+        ("×", Symbol::Operator(Operator::Multiply)),
+        ("·", Symbol::Operator(Operator::Multiply)),
+        ("/", Symbol::Operator(Operator::Divide)),
+        ("÷", Symbol::Operator(Operator::Divide)),
+        ("^", Symbol::Caret),
+        ("_", Symbol::Underscore),
+        (",", Symbol::Comma),
+        ("=", Symbol::Operator(Operator::IsEqualTo)),
+        ("<", Symbol::Operator(Operator::IsLessThan)),
+        (">", Symbol::Operator(Operator::IsGreaterThan)),
+        ("≤", Symbol::Operator(Operator::IsLessThanOrEqualTo)),
+        ("≥", Symbol::Operator(Operator::IsGreaterThanOrEqualTo)),
+        ("≠", Symbol::Operator(Operator::IsNotEqualTo)),
+        ("¬", Symbol::Operator(Operator::Not)),
+        ("∧", Symbol::Operator(Operator::And)),
+        ("∨", Symbol::Operator(Operator::Or)),
+        ("!", Symbol::Bang),
+        ("(", Symbol::Open(Delimiter::Paren)),
+        ("[", Symbol::Open(Delimiter::Bracket)),
+        ("{", Symbol::Open(Delimiter::Brace)),
+        (")", Symbol::Close),
+        ("]", Symbol::Close),
+        ("}", Symbol::Close),
+        ("|", Symbol::Bar),
+        (".", Symbol::Dot),
+        ("&", Symbol::Unsupported),
+        ("'", Symbol::Unsupported),
+    ];
+
+    SYMBOLS
+        .iter()
+        .find(|(text, _)| rest.starts_with(text))
+        .map(|(text, symbol)| (text.len(), symbol.clone()))
+}
+
+struct Reader<'e> {
+    expr: &'e str,
+    symbols: Peekable<vec::IntoIter<(Symbol, Span)>>,
+    end: Span,
+}
+
+impl Reader<'_> {
+    fn peek(&mut self) -> Option<&Symbol> {
+        self.symbols.peek().map(|(symbol, _)| symbol)
+    }
+
+    fn peek_span(&mut self) -> Span {
+        let end = self.end;
+        self.symbols.peek().map_or(end, |&(_, span)| span)
+    }
+
+    fn next(&mut self) -> Option<(Symbol, Span)> {
+        self.symbols.next()
+    }
+
+    fn next_if(&mut self, symbol: &Symbol) -> Option<Span> {
+        match self.symbols.peek() {
+            Some((next, span)) if next == symbol => {
+                let span = *span;
+                self.symbols.next();
+                Some(span)
+            },
+            _ => None,
+        }
+    }
+
+    fn text(&self, span: Span) -> String {
+        self.expr[span.start..span.end].to_string()
+    }
+
+    fn error(&self, err: Error) -> TokenList {
+        let span = err.span();
+        vec![SpannedToken::new(Token::Error(err), span)]
+    }
+
+    fn unsupported(&self, span: Span) -> TokenList {
+        self.error(Error::UnsupportedSyntax(self.text(span), span))
+    }
+
+    // Reads up to a closing delimiter, or up to a `|` closing an absolute
+    // value when `bar` is set.
+    fn expression(&mut self, tokens: &mut TokenList, bar: bool) {
+        loop {
+            let span = self.peek_span();
+            let token = match self.peek() {
+                None | Some(Symbol::Close) | Some(Symbol::Right) => return,
+                Some(Symbol::Bar) if bar && ends_operand(tokens) => return,
+                Some(Symbol::Minus) if ends_operand(tokens) => {
+                    Token::Operator(Operator::Substract)
+                },
+                Some(Symbol::Minus) => {
+                    tokens.push(SpannedToken::new(Token::Number(-1.0), span));
+                    Token::Operator(Operator::Multiply)
+                },
+                Some(Symbol::Operator(operator)) => Token::Operator(*operator),
+                Some(Symbol::Bang) | Some(Symbol::Percent)
+                    if ends_operand(tokens) =>
+                {
+                    self.postfix(tokens);
+                    continue;
+                },
+                Some(Symbol::Bang) => Token::Operator(Operator::Not),
+                Some(Symbol::Percent) => Token::Operator(Operator::Remainder),
+                Some(Symbol::Caret) => Token::Operator(Operator::Exponentiate),
+                Some(Symbol::Comma) => Token::Comma,
+                Some(Symbol::Underscore) => {
+                    let subscript = self.subscript();
+                    tokens.extend(self.unsupported(subscript));
+                    continue;
+                },
+                Some(_) => {
+                    let term = self.term();
+                    push_term(tokens, term);
+                    continue;
+                },
+            };
+            self.next();
+            tokens.push(SpannedToken::new(token, span));
+        }
+    }
+
+    // Reads a `!` or `\%` after an operand. `\%` is a remainder when an
+    // operand follows it.
+    fn postfix(&mut self, tokens: &mut TokenList) {
+        let (symbol, span) = match self.next() {
+            Some(next) => next,
+            None => return,
+        };
+        let adjacent = tokens.last().is_some_and(|last| {
+            last.token == Token::Operator(Operator::Factorial)
+                && last.span.end == span.start
+        });
+        // A `-` before the operand negates it, as in `10 \% -3`
+        let mut rest = self.symbols.clone().map(|(symbol, _)| symbol);
+        let operand_follows = match rest.next() {
+            Some(Symbol::Minus) => rest.next().is_some_and(|next| {
+                next.starts_term() && next != Symbol::Minus
+            }),
+            next => next.is_some_and(|next| next.starts_term()),
+        };
+
+        let (operator, span) = match symbol {
+            Symbol::Bang if adjacent => {
+                let last = tokens.pop().map_or(span, |last| last.span);
+                (Operator::DoubleFactorial, last.to(span))
+            },
+            Symbol::Bang => (Operator::Factorial, span),
+            _ if operand_follows => (Operator::Remainder, span),
+            _ => (Operator::Percent, span),
+        };
+        tokens.push(SpannedToken::new(Token::Operator(operator), span));
+    }
+
+    // An atom with its exponents.
+    fn term(&mut self) -> TokenList {
+        let mut term = self.atom();
+        while let Some(caret) = self.next_if(&Symbol::Caret) {
+            let exponent = self.argument(caret);
+            let power = Token::Operator(Operator::Exponentiate);
+            term.push(SpannedToken::new(power, caret));
+            term.extend(exponent);
+        }
+        term
+    }
+
+    // Implicitly multiplied terms with their postfix operators, as taken by
+    // a function without parentheses or by a sum.
+    fn product(&mut self) -> TokenList {
+        let mut tokens = self.term();
+        loop {
+            match self.peek() {
+                Some(Symbol::Bang) | Some(Symbol::Percent) => {
+                    self.postfix(&mut tokens)
+                },
+                Some(next) if next.continues_product() => {
+                    let term = self.term();
+                    push_term(&mut tokens, term);
+                },
+                _ => return tokens,
+            }
+        }
+    }
+
+    // The argument of `^`, `_`, `\frac` or `\sqrt`: a braced group or a
+    // single symbol.
+    fn argument(&mut self, owner: Span) -> TokenList {
+        match self.peek() {
+            Some(Symbol::Minus) => {
+                // Lenient about `x^-1`, which LaTeX sets as `x^{-}1`
+                let (_, span) = self.next().unwrap();
+                let mut negation =
+                    vec![SpannedToken::new(Token::Number(-1.0), span)];
+                negation.push(SpannedToken::new(
+                    Token::Operator(Operator::Multiply),
+                    span,
+                ));
+                negation.extend(self.argument(span));
+                negation
+            },
+            Some(next) if next.starts_term() => self.atom(),
+            _ => self.error(Error::MissingOperands(self.text(owner), owner)),
+        }
+    }
+
+    fn atom(&mut self) -> TokenList {
+        let (symbol, span) = match self.next() {
+            Some(next) => next,
+            None => return Vec::new(),
+        };
+        let token = |token| vec![SpannedToken::new(token, span)];
+
+        match symbol {
+            Symbol::Number(number) => token(number),
+            Symbol::Constant(name) => token(Token::Variable(name.into())),
+            Symbol::Name(name) => self.identifier(name, span),
+            Symbol::Minus => {
+                let mut negation = token(Token::Number(-1.0));
+                negation.push(SpannedToken::new(
+                    Token::Operator(Operator::Multiply),
+                    span,
+                ));
+                negation.extend(self.term());
+                negation
+            },
+            Symbol::Open(Delimiter::Floor) => self.group(Some("floor"), span),
+            Symbol::Open(Delimiter::Ceil) => self.group(Some("ceil"), span),
+            Symbol::Open(_) => self.group(None, span),
+            Symbol::Bar => self.group(Some("abs"), span),
+            Symbol::Left => self.left(span),
+            Symbol::Function(name) => self.function(name, span),
+            Symbol::Frac => {
+                let numerator = self.argument(span);
+                let denominator = self.argument(span);
+                divide(numerator, span, denominator)
+            },
+            Symbol::Sqrt => self.sqrt(span),
+            Symbol::Series(op) => self.series(op, span),
+            Symbol::Unsupported => self.unsupported(span),
+            Symbol::Invalid | Symbol::Dot => {
+                self.error(Error::InvalidToken(self.text(span), span))
+            },
+            _ => self.error(Error::UnexpectedToken(self.text(span), span)),
+        }
+    }
+
+    // A delimited expression, as the argument of `function` if given. An
+    // absolute value is closed by the next `|` ending an operand.
+    fn group(&mut self, function: Option<&str>, open: Span) -> TokenList {
+        let mut tokens = Vec::new();
+        if let Some(function) = function {
+            let function = Token::Function(function.into());
+            tokens.push(SpannedToken::new(function, open));
+        }
+        tokens.push(SpannedToken::new(Token::LeftParenthesis, open));
+
+        let mut inner = Vec::new();
+        let bar = function == Some("abs");
+        self.expression(&mut inner, bar);
+        tokens.extend(inner);
+
+        let close = if bar { Symbol::Bar } else { Symbol::Close };
+        if let Some(close) = self.next_if(&close) {
+            tokens.push(SpannedToken::new(Token::RightParenthesis, close));
+        }
+        tokens
+    }
+
+    // `\left( ... \right)`, with any delimiters on either side. `\left|`,
+    // `\left\lfloor` and `\left\lceil` take the absolute value, floor and
+    // ceiling.
+    fn left(&mut self, left: Span) -> TokenList {
+        let (delimiter, span) = match self.next() {
+            Some(next) => next,
+            None => return self.unsupported(left),
+        };
+        let open = left.to(span);
+        let function = match delimiter {
+            Symbol::Open(Delimiter::Floor) => Some("floor"),
+            Symbol::Open(Delimiter::Ceil) => Some("ceil"),
+            Symbol::Open(_) | Symbol::Dot => None,
+            Symbol::Bar => Some("abs"),
+            _ => return self.unsupported(open),
+        };
+
+        let mut tokens = Vec::new();
+        if let Some(function) = function {
+            let function = Token::Function(function.into());
+            tokens.push(SpannedToken::new(function, open));
+        }
+        tokens.push(SpannedToken::new(Token::LeftParenthesis, open));
+
+        let mut inner = Vec::new();
+        self.expression(&mut inner, false);
+        tokens.extend(inner);
+
+        if let Some(right) = self.next_if(&Symbol::Right) {
+            let close = match self.next() {
+                Some((_, delimiter)) => right.to(delimiter),
+                None => right,
+            };
+            tokens.push(SpannedToken::new(Token::RightParenthesis, close));
+        }
+        tokens
+    }
+
+    // A variable, with a subscript of letters and digits as in `x_1` or
+    // `x_{max}` naming the variable `x_1` or `x_max`.
+    fn identifier(&mut self, name: String, span: Span) -> TokenList {
+        if self.peek() != Some(&Symbol::Underscore) {
+            return vec![SpannedToken::new(Token::Variable(name), span)];
+        }
+
+        let start = self.symbols.clone();
+        let subscript = self.subscript();
+        // The symbols of the subscript, without its braces
+        let symbols = start
+            .skip(1)
+            .take_while(|(_, symbol_span)| symbol_span.end <= subscript.end)
+            .filter(|(symbol, _)| {
+                !matches!(
+                    symbol,
+                    Symbol::Open(Delimiter::Brace) | Symbol::Close
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut text = String::new();
+        for (symbol, symbol_span) in &symbols {
+            match symbol {
+                Symbol::Number(Token::Number(_)) => {
+                    text.push_str(&self.text(*symbol_span))
+                },
+                Symbol::Name(name) => text.push_str(name),
+                Symbol::Constant("E") => text.push('e'),
+                _ => return self.unsupported(span.to(subscript)),
+            }
+        }
+        // Only subscripts that the plain syntax reads back, so not `x_{1.5}`
+        if text.is_empty() || !text.chars().all(is_identifier_char) {
+            return self.unsupported(span.to(subscript));
+        }
+        let name = format!("{}_{}", name, text);
+        vec![SpannedToken::new(Token::Variable(name), span.to(subscript))]
+    }
+
+    // Consumes a `_` and its argument, returning their span.
+    fn subscript(&mut self) -> Span {
+        let (_, underscore) = self.next().unwrap();
+        let argument = self.argument(underscore);
+        argument
+            .last()
+            .map_or(underscore, |last| underscore.to(last.span))
+    }
+
+    // A function applied to the parenthesized expression after it, or else
+    // to the product that follows it, as in `\sin 2x`. `\log_{b} x` is the
+    // logarithm of `x` in base `b`, and `\sin^{2} x` is `\sin(x)^{2}`.
+    fn function(&mut self, name: String, span: Span) -> TokenList {
+        let mut base = None;
+        if name == "log" {
+            if let Some(underscore) = self.next_if(&Symbol::Underscore) {
+                base = Some(self.argument(underscore));
+            }
+        }
+        let mut exponent = None;
+        if let Some(caret) = self.next_if(&Symbol::Caret) {
+            let power = self.argument(caret);
+            let inverse = power
+                .iter()
+                .find(|token| token.token != Token::LeftParenthesis)
+                .is_some_and(|token| token.token == Token::Number(-1.0));
+            if inverse {
+                // `\sin^{-1} x` is the inverse function
+                let end = power.last().map_or(caret, |last| last.span);
+                return self.unsupported(span.to(end));
+            }
+            exponent = Some((caret, power));
+        }
+
+        let argument = match self.peek() {
+            Some(Symbol::Open(Delimiter::Paren))
+            | Some(Symbol::Open(Delimiter::Bracket))
+            | Some(Symbol::Open(Delimiter::Brace)) => {
+                let (_, open) = self.next().unwrap();
+                self.group(None, open)
+            },
+            Some(Symbol::Left) => {
+                let (_, left) = self.next().unwrap();
+                let group = self.left(left);
+                match group.first().map(|first| &first.token) {
+                    Some(Token::LeftParenthesis) => group,
+                    _ => parenthesized(group, span),
+                }
+            },
+            Some(next) if next.starts_term() => {
+                let product = self.product();
+                parenthesized(product, span)
+            },
+            _ => {
+                let err = Error::NotEnoughFunctionParams(name, span);
+                return self.error(err);
+            },
+        };
+
+        let mut tokens = Vec::new();
+        match base {
+            Some(base) => {
+                let (start, _) = bounds(&base, span);
+                let (_, end) = bounds(&argument, span);
+                tokens.push(SpannedToken::new(Token::Function(name), span));
+                tokens.push(SpannedToken::new(Token::LeftParenthesis, start));
+                tokens.extend(base);
+                tokens.push(SpannedToken::new(Token::Comma, start));
+                tokens.extend(argument);
+                tokens.push(SpannedToken::new(Token::RightParenthesis, end));
+            },
+            None => {
+                // A logarithm without a base is a common logarithm
+                let name = if name == "log" { "log_10".into() } else { name };
+                tokens.push(SpannedToken::new(Token::Function(name), span));
+                tokens.extend(argument);
+            },
+        }
+        if let Some((caret, exponent)) = exponent {
+            let power = Token::Operator(Operator::Exponentiate);
+            tokens.push(SpannedToken::new(power, caret));
+            tokens.extend(exponent);
+        }
+        tokens
+    }
+
+    // `\sqrt[n]{x}` is `sqrt(x)` or `cbrt(x)` for a literal 2 or 3 and
+    // `x^(1/n)` otherwise.
+    fn sqrt(&mut self, span: Span) -> TokenList {
+        let mut index = Vec::new();
+        if let Some(open) = self.next_if(&Symbol::Open(Delimiter::Bracket)) {
+            self.expression(&mut index, false);
+            if self.next_if(&Symbol::Close).is_none() {
+                let err = Error::UnbalancedParentheses(open);
+                return self.error(err);
+            }
+        }
+        let radicand = self.argument(span);
+
+        let literal = index.iter().map(|token| &token.token);
+        let function = match literal.collect::<Vec<_>>().as_slice() {
+            [] => Some("sqrt"),
+            [Token::Number(n)] if *n == 2.0 => Some("sqrt"),
+            [Token::Number(n)] if *n == 3.0 => Some("cbrt"),
+            _ => None,
+        };
+        if let Some(function) = function {
+            let function = Token::Function(function.into());
+            let mut tokens = vec![SpannedToken::new(function, span)];
+            tokens.extend(parenthesized(radicand, span));
+            return tokens;
+        }
+
+        let (start, end) = bounds(&index, span);
+        let mut exponent = vec![SpannedToken::new(Token::Number(1.0), start)];
+        exponent
+            .push(SpannedToken::new(Token::Operator(Operator::Divide), start));
+        exponent.extend(parenthesized(index, span));
+        let mut tokens = parenthesized(radicand, span);
+        tokens.push(SpannedToken::new(
+            Token::Operator(Operator::Exponentiate),
+            end,
+        ));
+        tokens.extend(parenthesized(exponent, span));
+        tokens
+    }
+
+    // `\sum_{i=a}^{b} body` is a `Token::Series` applied to `(i, a, b,
+    // body)`, where the body runs up to the next explicit operator or
+    // function.
+    fn series(&mut self, op: SeriesOp, span: Span) -> TokenList {
+        let (index, lower, upper) = match self.series_bounds(span) {
+            Ok(bounds) => bounds,
+            Err(end) => return self.unsupported(span.to(end)),
+        };
+
+        let body = match self.peek() {
+            Some(next) if next.starts_term() => self.product(),
+            _ => {
+                let err = Error::MissingOperands(self.text(span), span);
+                return self.error(err);
+            },
+        };
+
+        let (_, end) = bounds(&body, span);
+        let mut tokens = vec![
+            SpannedToken::new(Token::Series(op), span),
+            SpannedToken::new(Token::LeftParenthesis, span),
+            index,
+            SpannedToken::new(Token::Comma, span),
+        ];
+        tokens.extend(lower);
+        tokens.push(SpannedToken::new(Token::Comma, span));
+        tokens.extend(upper);
+        tokens.push(SpannedToken::new(Token::Comma, span));
+        tokens.extend(body);
+        tokens.push(SpannedToken::new(Token::RightParenthesis, end));
+        tokens
+    }
+
+    // Reads `_{i=a}` and `^{b}`, in either order, returning the index and
+    // the bounds, or the end of what was read when they have another form.
+    fn series_bounds(
+        &mut self,
+        span: Span,
+    ) -> Result<(SpannedToken, TokenList, TokenList), Span> {
+        let mut end = span;
+        let mut subscript = None;
+        let mut upper = None;
+        loop {
+            if let Some(underscore) = self.next_if(&Symbol::Underscore) {
+                let tokens = self.argument(underscore);
+                end = tokens.last().map_or(underscore, |last| last.span);
+                subscript = Some(tokens);
+            }
+            else if let Some(caret) = self.next_if(&Symbol::Caret) {
+                let tokens = self.argument(caret);
+                end = tokens.last().map_or(caret, |last| last.span);
+                upper = Some(tokens);
+            }
+            else {
+                break;
+            }
+        }
+
+        let (mut subscript, upper) = match (subscript, upper) {
+            (Some(subscript), Some(upper)) => (subscript, upper),
+            _ => return Err(end),
+        };
+        let is_index = subscript.len() > 4
+            && matches!(
+                subscript.as_slice(),
+                [
+                    SpannedToken {
+                        token: Token::LeftParenthesis,
+                        ..
+                    },
+                    SpannedToken {
+                        token: Token::Variable(_),
+                        ..
+                    },
+                    SpannedToken {
+                        token: Token::Operator(Operator::IsEqualTo),
+                        ..
+                    },
+                    ..,
+                    SpannedToken {
+                        token: Token::RightParenthesis,
+                        ..
+                    },
+                ]
+            );
+        if !is_index {
+            return Err(end);
+        }
+        subscript.pop();
+        let lower = subscript.split_off(3);
+        let index = subscript.swap_remove(1);
+        Ok((index, lower, upper))
+    }
+}
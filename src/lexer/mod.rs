@@ -1,24 +1,63 @@
+mod asciimath;
+mod latex;
+mod number;
+
+use self::number::{consume_number, starts_number, Chars};
 use crate::{
-    ast::{resolve_fn, resolve_var, NumericLiteral, Scope},
+    ast::{is_known_fn, is_known_var, is_lazy_fn, Names, NumericLiteral},
+    constants::is_greek_name,
     error::Error,
-    tokens::{Operator, Token, TokenList},
+    operators::{Fixity, OperatorTable},
+    options::{ImplicitMultiplication, ParserOptions, Syntax},
+    span::Span,
+    tokens::{Operator, SpannedToken, Token, TokenList},
     util::consume_while,
 };
 
-fn resolve_vars(expr: &str, scope: &Scope, mut tokens: &mut Vec<Token>) {
-    let mut chars = expr.chars();
+// Pushes an operand found within a word, multiplying it with the operand
+// before it unless it is the first one in the word.
+fn push_implicit(
+    tokens: &mut TokenList,
+    word_start: usize,
+    token: SpannedToken,
+) {
+    if tokens.len() > word_start {
+        let at = token.span.start;
+        tokens.push(SpannedToken::new(
+            Token::Operator(Operator::Multiply),
+            Span::new(at, at),
+        ));
+    }
+    tokens.push(token);
+}
+
+fn resolve_vars(
+    expr: &str,
+    offset: usize,
+    names: &dyn Names,
+    word_start: usize,
+    tokens: &mut TokenList,
+) {
+    let mut chars = expr.char_indices();
     let mut var = String::new();
+    let mut start = offset;
     let mut is_valid_var = false;
 
-    let new_var = |name, t: &mut Vec<Token>| {
-        t.push(Token::Variable(name));
-        t.push(Token::Operator(Operator::Multiply));
+    let new_var = |name, span: Span, t: &mut TokenList| {
+        push_implicit(
+            t,
+            word_start,
+            SpannedToken::new(Token::Variable(name), span),
+        )
     };
 
     loop {
-        if let Some(c) = chars.next() {
+        if let Some((i, c)) = chars.next() {
+            if var.is_empty() {
+                start = offset + i;
+            }
             var.push(c);
-            is_valid_var = resolve_var(&var, scope).is_ok();
+            is_valid_var = is_known_var(&var, names) || is_greek_name(&var);
             if !is_valid_var {
                 continue;
             }
@@ -26,11 +65,13 @@ fn resolve_vars(expr: &str, scope: &Scope, mut tokens: &mut Vec<Token>) {
 
         if !var.is_empty() {
             if is_valid_var {
-                new_var(var.clone(), &mut tokens);
+                let span = Span::new(start, start + var.len());
+                new_var(var.clone(), span, tokens);
             }
             else {
-                for c in var.chars() {
-                    new_var(c.to_string(), &mut tokens);
+                for (i, c) in var.char_indices() {
+                    let span = Span::new(start + i, start + i + c.len_utf8());
+                    new_var(c.to_string(), span, tokens);
                 }
             }
 
@@ -43,115 +84,735 @@ fn resolve_vars(expr: &str, scope: &Scope, mut tokens: &mut Vec<Token>) {
 
 fn parse_implicit(
     expr: &str,
-    scope: &Scope,
+    offset: usize,
+    names: &dyn Names,
+    options: &ParserOptions,
     tokens: &mut TokenList,
-) -> Result<(), Error> {
-    let mut chars = expr.chars().peekable();
+) {
+    let mut chars = expr.char_indices().peekable();
 
-    if tokens.last() == Some(&Token::RightParenthesis) {
-        tokens.push(Token::Operator(Operator::Multiply));
-    }
+    after_paren(tokens);
+    let word_start = tokens.len();
 
-    while let Some(&ch) = chars.peek() {
+    while let Some(&(i, ch)) = chars.peek() {
+        let start = offset + i;
         match ch {
-            '0'...'9' => {
-                let num = consume_while(chars.by_ref(), |n| {
-                    n.is_digit(10) || n == '.'
-                });
-                let n = num
-                    .parse::<NumericLiteral>()
-                    .map_err(|_e| Error::InvalidToken(num))?;
-                tokens.push(Token::Number(n));
-                tokens.push(Token::Operator(Operator::Multiply));
+            '0'..='9' => {
+                let number = consume_number(&mut chars, offset);
+                push_implicit(tokens, word_start, number);
+            },
+            c if c.is_alphabetic() || c == '_' => {
+                let (raw, name) = consume_identifier(&mut chars);
+                let letters = name.chars().all(char::is_alphabetic);
+                if letters
+                    && !is_greek_name(&name)
+                    && options.implicit_multiplication
+                        == ImplicitMultiplication::Full
+                {
+                    resolve_vars(&name, start, names, word_start, tokens);
+                }
+                else {
+                    let span = Span::new(start, start + raw.len());
+                    let var = SpannedToken::new(Token::Variable(name), span);
+                    push_implicit(tokens, word_start, var);
+                }
             },
-            'a'...'z' | 'A'...'Z' => {
-                let vars = consume_while(&mut chars, |c| c.is_alphabetic());
-                resolve_vars(&vars, scope, tokens);
-                chars.by_ref().next();
+            _ => {
+                chars.next();
+                if options.strict {
+                    let span = Span::new(start, start + ch.len_utf8());
+                    tokens.push(invalid(ch, span));
+                }
             },
-            _ => {},
         }
     }
-    Ok(())
 }
 
-fn get_token(ch: Option<&char>, t: &mut Vec<Token>) -> Option<Token> {
-    if let Some(ch) = ch {
-        match ch {
-            '+' => Some(Token::Operator(Operator::Add)),
-            '-' => match t.last() {
-                Some(Token::Comma)
-                | Some(Token::LeftParenthesis)
-                | Some(Token::Function(_))
-                | Some(Token::Operator(_))
-                | None => {
-                    t.push(Token::Number(-1.0));
-                    t.push(Token::Operator(Operator::Multiply));
-                    None
-                },
-                _ => Some(Token::Operator(Operator::Substract)),
+fn is_identifier_char(c: char) -> bool {
+    is_word_char(c) && c != '.'
+}
+
+// Reads an identifier: a letter or `_` followed by letters, digits and `_`.
+// A braced subscript as in `x_{ij}` is part of it, and names the same
+// variable as `x_ij`. Returns the identifier as written and its name.
+fn consume_identifier(chars: &mut Chars) -> (String, String) {
+    let mut raw = consume_while(chars, is_identifier_char);
+    let mut name = raw.clone();
+
+    while raw.ends_with('_') && subscript_follows(chars) {
+        chars.next();
+        let subscript = consume_while(chars, is_identifier_char);
+        chars.next();
+        raw.push_str(&format!("{{{}}}", subscript));
+        name.push_str(&subscript);
+
+        let rest = consume_while(chars, is_identifier_char);
+        raw.push_str(&rest);
+        name.push_str(&rest);
+    }
+    (raw, name)
+}
+
+// Whether a closed `{...}` subscript comes next.
+fn subscript_follows(chars: &Chars) -> bool {
+    let mut ahead = chars.clone().map(|(_, c)| c);
+    ahead.next() == Some('{')
+        && ahead.find(|&c| !is_identifier_char(c)) == Some('}')
+}
+
+fn invalid(ch: char, span: Span) -> SpannedToken {
+    SpannedToken::new(
+        Token::Error(Error::InvalidToken(ch.to_string(), span)),
+        span,
+    )
+}
+
+// Implicit multiplication of an operand following a closing parenthesis.
+fn after_paren(tokens: &mut TokenList) {
+    if let Some(last) = tokens.last() {
+        if last.token == Token::RightParenthesis {
+            let span = Span::new(last.span.end, last.span.end);
+            tokens.push(SpannedToken::new(
+                Token::Operator(Operator::Multiply),
+                span,
+            ));
+        }
+    }
+}
+
+// Whether the last token completes an operand, so that a following `!` or
+// `%` is postfix.
+fn ends_operand(tokens: &TokenList) -> bool {
+    match tokens.last().map(|last| &last.token) {
+        Some(Token::Number(_))
+        | Some(Token::Variable(_))
+        | Some(Token::RightParenthesis) => true,
+        Some(Token::Operator(operator)) => operator.is_postfix(),
+        _ => false,
+    }
+}
+
+// Whether an operand comes next, skipping whitespace and a minus sign.
+// Decides whether `%` is a remainder or a percentage, as in `50% mod 3` or
+// `10 % -3`.
+fn operand_follows(chars: &Chars, operators: &OperatorTable) -> bool {
+    let mut rest = chars
+        .clone()
+        .map(|(_, c)| c)
+        .skip_while(|c| c.is_whitespace())
+        .peekable();
+    if rest.next_if(|c| matches!(c, '-' | '−')).is_some() {
+        while rest.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+    let word: String = rest.clone().take_while(|c| is_word_char(*c)).collect();
+    rest.peek().is_some_and(|c| starts_operand(*c))
+        && word_operator(&word).is_none()
+        && operators.infix(&word).is_none()
+}
+
+fn get_token(
+    ch: char,
+    span: Span,
+    t: &mut TokenList,
+    operators: &OperatorTable,
+) -> Option<SpannedToken> {
+    let token = |token| Some(SpannedToken::new(token, span));
+    let merge = |t: &mut TokenList, operator| {
+        let prev = t.pop().map_or(span, |prev| prev.span);
+        Some(SpannedToken::new(Token::Operator(operator), prev.to(span)))
+    };
+
+    match ch {
+        '+' => token(Token::Operator(Operator::Add)),
+        '-' | '−' => match t.last().map(|last| &last.token) {
+            Some(Token::Operator(operator)) if operator.is_postfix() => {
+                token(Token::Operator(Operator::Substract))
             },
-            '*' => Some(Token::Operator(Operator::Multiply)),
-            '/' => Some(Token::Operator(Operator::Divide)),
-            '^' => Some(Token::Operator(Operator::Exponentiate)),
-            '>' => Some(Token::Operator(Operator::IsGreaterThan)),
-            '<' => Some(Token::Operator(Operator::IsLessThan)),
-            '=' => match t.last() {
-                Some(Token::Operator(Operator::Not)) => {
-                    t.pop();
-                    Some(Token::Operator(Operator::IsNotEqualTo))
-                },
-                Some(Token::Operator(Operator::IsGreaterThan)) => {
-                    t.pop();
-                    Some(Token::Operator(Operator::IsGreaterThanOrEqualTo))
-                },
-                Some(Token::Operator(Operator::IsLessThan)) => {
-                    t.pop();
-                    Some(Token::Operator(Operator::IsLessThanOrEqualTo))
-                },
-                Some(Token::Operator(Operator::IsEqualTo)) => None,
-                _ => Some(Token::Operator(Operator::IsEqualTo)),
+            Some(Token::Comma)
+            | Some(Token::Symbol(_))
+            | Some(Token::QuestionMark)
+            | Some(Token::Colon)
+            | Some(Token::LeftParenthesis)
+            | Some(Token::Function(_))
+            | Some(Token::Operator(_))
+            | None => {
+                t.push(SpannedToken::new(Token::Number(-1.0), span));
+                token(Token::Operator(Operator::Multiply))
+            },
+            _ => token(Token::Operator(Operator::Substract)),
+        },
+        '*' | '×' | '·' | '⋅' => token(Token::Operator(Operator::Multiply)),
+        '/' => match t.last() {
+            Some(SpannedToken {
+                token: Token::Operator(Operator::Divide),
+                span: prev,
+            }) if prev.end == span.start => merge(t, Operator::FloorDivide),
+            _ => token(Token::Operator(Operator::Divide)),
+        },
+        '÷' => token(Token::Operator(Operator::Divide)),
+        '%' => token(Token::Operator(Operator::Remainder)),
+        '^' => token(Token::Operator(Operator::Exponentiate)),
+        '>' => token(Token::Operator(Operator::IsGreaterThan)),
+        '<' => token(Token::Operator(Operator::IsLessThan)),
+        '≥' => token(Token::Operator(Operator::IsGreaterThanOrEqualTo)),
+        '≤' => token(Token::Operator(Operator::IsLessThanOrEqualTo)),
+        '≠' => token(Token::Operator(Operator::IsNotEqualTo)),
+        '=' => match t.last().map(|last| &last.token) {
+            Some(Token::Operator(Operator::Not)) => {
+                merge(t, Operator::IsNotEqualTo)
             },
-            '(' => Some(Token::LeftParenthesis),
-            ')' => Some(Token::RightParenthesis),
-            ',' => Some(Token::Comma),
-            '!' => Some(Token::Operator(Operator::Not)),
-            _ => None,
+            Some(Token::Operator(Operator::Factorial))
+                if t.last().is_some_and(|last| last.span.end == span.start) =>
+            {
+                merge(t, Operator::IsNotEqualTo)
+            },
+            Some(Token::Operator(Operator::IsGreaterThan)) => {
+                merge(t, Operator::IsGreaterThanOrEqualTo)
+            },
+            Some(Token::Operator(Operator::IsLessThan)) => {
+                merge(t, Operator::IsLessThanOrEqualTo)
+            },
+            Some(Token::Operator(Operator::IsEqualTo)) => {
+                merge(t, Operator::IsEqualTo)
+            },
+            _ => token(Token::Operator(Operator::IsEqualTo)),
+        },
+        '(' => token(Token::LeftParenthesis),
+        ')' => token(Token::RightParenthesis),
+        ',' => token(Token::Comma),
+        '?' => token(Token::QuestionMark),
+        ':' => token(Token::Colon),
+        '!' => match t.last() {
+            Some(SpannedToken {
+                token: Token::Operator(Operator::Factorial),
+                span: prev,
+            }) if prev.end == span.start => merge(t, Operator::DoubleFactorial),
+            _ if ends_any_operand(t, operators) => {
+                token(Token::Operator(Operator::Factorial))
+            },
+            _ => token(Token::Operator(Operator::Not)),
+        },
+        '¬' => token(Token::Operator(Operator::Not)),
+        '∧' => token(Token::Operator(Operator::And)),
+        '∨' => token(Token::Operator(Operator::Or)),
+        '⊻' => token(Token::Operator(Operator::Xor)),
+        _ => None,
+    }
+}
+
+pub fn tokenize(
+    expr: &str,
+    names: &dyn Names,
+    options: &ParserOptions,
+    operators: &OperatorTable,
+) -> Result<TokenList, Error> {
+    let tokens = tokenize_recovering(expr, names, options, operators);
+    for token in &tokens {
+        if let Token::Error(ref err) = token.token {
+            return Err(err.clone());
         }
     }
-    else {
-        None
+    Ok(tokens)
+}
+
+/// Tokenizes `expr`, leaving a `Token::Error` in place of any invalid input.
+pub fn tokenize_recovering(
+    expr: &str,
+    names: &dyn Names,
+    options: &ParserOptions,
+    operators: &OperatorTable,
+) -> TokenList {
+    let mut tokens = match options.syntax {
+        Syntax::Plain => tokenize_plain(expr, names, options, operators),
+        Syntax::AsciiMath => asciimath::tokenize(expr, names, options.strict),
+        Syntax::Latex => latex::tokenize(expr, names, options.strict),
+    };
+    disable_operators(expr, &mut tokens, operators);
+    match options.implicit_multiplication {
+        ImplicitMultiplication::Disabled => {
+            for token in tokens.iter_mut().filter(|token| is_implicit(token)) {
+                token.token =
+                    Token::Error(Error::ImplicitMultiplication(token.span));
+            }
+        },
+        // The plain lexer only implies these in the first place
+        ImplicitMultiplication::NumbersAndParens
+            if options.syntax != Syntax::Plain =>
+        {
+            restrict_implicit(&mut tokens)
+        },
+        _ => {},
+    }
+    tokens
+}
+
+// Rejects implied multiplications that are not next to a number or a group.
+// The front-ends turn braces and fractions into parentheses, so `2\pi` and
+// `\frac{1}{2}x` are allowed while `\pi r` is not.
+fn restrict_implicit(tokens: &mut TokenList) {
+    for i in 1..tokens.len().saturating_sub(1) {
+        let allowed = matches!(
+            tokens[i - 1].token,
+            Token::Number(_) | Token::RightParenthesis
+        ) || matches!(
+            tokens[i + 1].token,
+            Token::Number(_) | Token::LeftParenthesis
+        );
+        if is_implicit(&tokens[i]) && !allowed {
+            let span = tokens[i].span;
+            tokens[i].token = Token::Error(Error::ImplicitMultiplication(span));
+        }
     }
 }
 
-pub fn tokenize<'a>(expr: &str, scope: &'a Scope) -> Result<TokenList, Error> {
-    dbg!(expr);
-    let mut chars = expr.chars().peekable();
+fn tokenize_plain(
+    expr: &str,
+    names: &dyn Names,
+    options: &ParserOptions,
+    operators: &OperatorTable,
+) -> TokenList {
+    let mut chars = expr.char_indices().peekable();
     let mut tokens = Vec::with_capacity(expr.len());
+    // Parenthesis depth of each radical still waiting for its operand
+    let mut radicals: Vec<usize> = Vec::new();
+    // Parenthesis depth inside each absolute value bar still open
+    let mut bars: Vec<usize> = Vec::new();
+    let mut depth = 0;
 
-    while let Some(&_c) = chars.peek() {
-        let temp = consume_while(&mut chars.by_ref(), |c| {
-            c.is_alphanumeric() || c == '_' || c == '.'
-        });
+    while let Some(&(start, ch)) = chars.peek() {
+        let span = Span::new(start, start + ch.len_utf8());
+
+        if starts_number(&chars) {
+            after_paren(&mut tokens);
+            tokens.push(consume_number(&mut chars, 0));
+            end_operand(&mut chars, &mut tokens, &mut radicals, depth);
+            continue;
+        }
+
+        if let Some(len) = operators.match_symbol(&expr[start..]) {
+            let symbol = &expr[start..start + len];
+            while chars.peek().is_some_and(|&(i, _)| i < start + len) {
+                chars.next();
+            }
+            let span = Span::new(start, start + len);
+            tokens.push(SpannedToken::new(Token::Symbol(symbol.into()), span));
+            continue;
+        }
+
+        // A custom postfix operator ends an operand
+        if ch == '-' && ends_custom_operand(&tokens, operators) {
+            chars.next();
+            let minus = Token::Operator(Operator::Substract);
+            tokens.push(SpannedToken::new(minus, span));
+            continue;
+        }
+
+        if let Some(name) = unicode_constant(ch) {
+            chars.next();
+            after_paren(&mut tokens);
+            tokens.push(SpannedToken::new(Token::Variable(name.into()), span));
+            end_operand(&mut chars, &mut tokens, &mut radicals, depth);
+            continue;
+        }
+
+        if let Some(name) = unicode_root(ch) {
+            chars.next();
+            after_paren(&mut tokens);
+            tokens.push(SpannedToken::new(Token::Function(name.into()), span));
+            if chars.peek().map(|&(_, c)| c) != Some('(') {
+                let paren = Span::new(span.end, span.end);
+                tokens.push(SpannedToken::new(Token::LeftParenthesis, paren));
+                radicals.push(depth);
+            }
+            continue;
+        }
+
+        let mut temp = consume_while(chars.by_ref(), is_word_char);
+        while temp.ends_with('_') && subscript_follows(&chars) {
+            let end = chars.clone().position(|(_, c)| c == '}').unwrap();
+            temp.extend(chars.by_ref().take(end + 1).map(|(_, c)| c));
+            temp.push_str(&consume_while(chars.by_ref(), is_word_char));
+        }
+        let next = chars.peek().map(|&(_, c)| c);
 
         if !temp.is_empty() {
-            if chars.peek() == Some(&'(') && resolve_fn(&temp, scope).is_ok() {
-                tokens.push(Token::Function(temp));
-                continue;
+            if operators.is_symbol(&temp) {
+                let span = Span::new(start, start + temp.len());
+                tokens.push(SpannedToken::new(Token::Symbol(temp), span));
             }
-            else {
-                parse_implicit(&temp, scope, &mut tokens)?;
-                if chars.peek() != Some(&'(') {
-                    tokens.pop();
+            else if let Some(operator) = word_operator(&temp) {
+                let span = Span::new(start, start + temp.len());
+                tokens.push(SpannedToken::new(Token::Operator(operator), span));
+            }
+            else if (next == Some('(') || options.function_application)
+                && (is_known_fn(&temp, names) || is_lazy_fn(&temp))
+            {
+                let span = Span::new(start, start + temp.len());
+                // As in `2 sin x` or `sin x sin y`, a function name after an
+                // operand multiplies it even across a space
+                if options.function_application
+                    && ends_any_operand(&tokens, operators)
+                {
+                    let last = tokens.last().unwrap();
+                    let at = Span::new(last.span.end, last.span.end);
+                    let allowed = options.implicit_multiplication
+                        == ImplicitMultiplication::Full
+                        || matches!(
+                            last.token,
+                            Token::Number(_) | Token::RightParenthesis
+                        );
+                    let token = if allowed {
+                        Token::Operator(Operator::Multiply)
+                    }
+                    else {
+                        Token::Error(Error::ImplicitMultiplication(at))
+                    };
+                    tokens.push(SpannedToken::new(token, at));
                 }
+                after_paren(&mut tokens);
+                tokens.push(SpannedToken::new(Token::Function(temp), span));
+            }
+            else {
+                parse_implicit(&temp, start, names, options, &mut tokens);
+                end_operand(&mut chars, &mut tokens, &mut radicals, depth);
             }
+            continue;
+        }
+
+        if ch == '|'
+            && ends_any_operand(&tokens, operators)
+            && bars.last() == Some(&depth)
+        {
+            chars.next();
+            bars.pop();
+            depth -= 1;
+            tokens.push(SpannedToken::new(Token::RightParenthesis, span));
+            exponent(&mut chars, &mut tokens);
+            close_radicals(&mut tokens, &mut radicals, depth);
+            continue;
+        }
+
+        // `||` is only an `or` between two operands
+        let or = doubled_operator(&chars) == Some(Operator::Or);
+        if ch == '|' && !(or && ends_any_operand(&tokens, operators)) {
+            chars.next();
+            // Like a stray `)`, a bar that can only close but has nothing to
+            // close. A bar that opens is reported by the parser if unclosed.
+            let rest = &expr[span.end..];
+            let closes = ends_any_operand(&tokens, operators);
+            if !rest.contains('|') && (closes || rest.trim().is_empty()) {
+                let err = Error::UnbalancedParentheses(span);
+                tokens.push(SpannedToken::new(Token::Error(err), span));
+                continue;
+            }
+            if ends_any_operand(&tokens, operators) {
+                let at = Span::new(start, start);
+                let multiply = Token::Operator(Operator::Multiply);
+                tokens.push(SpannedToken::new(multiply, at));
+            }
+            depth += 1;
+            bars.push(depth);
+            tokens.push(SpannedToken::new(Token::Function("abs".into()), span));
+            tokens.push(SpannedToken::new(Token::LeftParenthesis, span));
+            continue;
         }
-        if let Some(token) = get_token(chars.peek(), &mut tokens) {
-            tokens.push(token);
+
+        if let Some(operator) = doubled_operator(&chars) {
+            chars.nth(1);
+            let span = Span::new(start, start + 2);
+            tokens.push(SpannedToken::new(Token::Operator(operator), span));
+            continue;
         }
+
         chars.next();
+        if ch == '%'
+            && ends_any_operand(&tokens, operators)
+            && !operand_follows(&chars, operators)
+        {
+            let percent = Token::Operator(Operator::Percent);
+            tokens.push(SpannedToken::new(percent, span));
+            continue;
+        }
+        if let Some(token) = get_token(ch, span, &mut tokens, operators) {
+            match token.token {
+                Token::LeftParenthesis => {
+                    after_paren(&mut tokens);
+                    depth += 1;
+                    tokens.push(token);
+                },
+                Token::RightParenthesis => {
+                    depth = depth.saturating_sub(1);
+                    tokens.push(token);
+                    exponent(&mut chars, &mut tokens);
+                    close_radicals(&mut tokens, &mut radicals, depth);
+                },
+                _ => tokens.push(token),
+            }
+        }
+        else if options.strict && !ch.is_whitespace() {
+            tokens.push(invalid(ch, span));
+        }
     }
 
-    Ok(tokens)
+    tokens
 }
+
+/// Whether the token is a multiplication implied by two adjacent operands.
+/// These are the only zero-width `*`.
+pub fn is_implicit(token: &SpannedToken) -> bool {
+    token.span.is_empty() && token.token == Token::Operator(Operator::Multiply)
+}
+
+fn ends_custom_operand(tokens: &TokenList, operators: &OperatorTable) -> bool {
+    match tokens.last().map(|last| &last.token) {
+        Some(Token::Symbol(symbol)) => operators
+            .infix(symbol)
+            .is_some_and(|custom| custom.fixity == Fixity::Postfix),
+        _ => false,
+    }
+}
+
+// Whether the last token completes an operand, custom postfix operators
+// included.
+fn ends_any_operand(tokens: &TokenList, operators: &OperatorTable) -> bool {
+    ends_operand(tokens) || ends_custom_operand(tokens, operators)
+}
+
+// Replaces disabled builtin operators with errors. A unary minus, lexed as
+// `-1 *`, counts as a `-`.
+fn disable_operators(
+    expr: &str,
+    tokens: &mut TokenList,
+    operators: &OperatorTable,
+) {
+    for i in 0..tokens.len() {
+        let (operator, span) = match tokens[i] {
+            SpannedToken {
+                token: Token::Operator(operator),
+                span,
+            } if !span.is_empty() => (operator, span),
+            _ => continue,
+        };
+        let negation = i > 0
+            && tokens[i - 1].span == span
+            && tokens[i - 1].token == Token::Number(-1.0);
+        let operator = if negation {
+            Operator::Substract
+        }
+        else {
+            operator
+        };
+
+        if operators.is_disabled(&operator) {
+            let text = expr[span.start..span.end].to_string();
+            let err = Token::Error(Error::InvalidToken(text, span));
+            tokens[i].token = err;
+        }
+    }
+}
+
+fn word_operator(word: &str) -> Option<Operator> {
+    match word {
+        "mod" => Some(Operator::Modulo),
+        "and" => Some(Operator::And),
+        "or" => Some(Operator::Or),
+        "xor" => Some(Operator::Xor),
+        _ => None,
+    }
+}
+
+// `&` and `|` are only operators when doubled.
+fn doubled_operator(chars: &Chars) -> Option<Operator> {
+    let mut ahead = chars.clone().map(|(_, c)| c);
+    match (ahead.next(), ahead.next()) {
+        (Some('&'), Some('&')) => Some(Operator::And),
+        (Some('|'), Some('|')) => Some(Operator::Or),
+        _ => None,
+    }
+}
+
+fn unicode_constant(c: char) -> Option<&'static str> {
+    match c {
+        'π' => Some("PI"),
+        '∞' => Some("INFINITY"),
+        _ => None,
+    }
+}
+
+fn unicode_root(c: char) -> Option<&'static str> {
+    match c {
+        '√' => Some("sqrt"),
+        '∛' => Some("cbrt"),
+        _ => None,
+    }
+}
+
+fn superscript(c: char) -> Option<char> {
+    match c {
+        '⁰' => Some('0'),
+        '¹' => Some('1'),
+        '²' => Some('2'),
+        '³' => Some('3'),
+        '⁴' => Some('4'),
+        '⁵' => Some('5'),
+        '⁶' => Some('6'),
+        '⁷' => Some('7'),
+        '⁸' => Some('8'),
+        '⁹' => Some('9'),
+        '⁻' => Some('-'),
+        _ => None,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    (c.is_alphanumeric() || c == '_' || c == '.')
+        && superscript(c).is_none()
+        && unicode_constant(c).is_none()
+}
+
+fn starts_operand(c: char) -> bool {
+    is_word_char(c)
+        || c == '('
+        || unicode_constant(c).is_some()
+        || unicode_root(c).is_some()
+}
+
+// Superscript digits, as in `x²` or `x⁻¹`, become an exponent. A leading
+// `⁻` is a unary minus, lexed like `-`.
+fn exponent(chars: &mut Chars, tokens: &mut TokenList) {
+    let start = match chars.peek() {
+        Some(&(i, c)) if superscript(c).is_some() => i,
+        _ => return,
+    };
+    let mut text = String::new();
+    let mut digits = String::new();
+    while let Some(&(_, c)) = chars.peek() {
+        match superscript(c) {
+            Some(digit) => {
+                text.push(c);
+                digits.push(digit);
+                chars.next();
+            },
+            None => break,
+        }
+    }
+
+    let span = Span::new(start, start + text.len());
+    tokens.push(SpannedToken::new(
+        Token::Operator(Operator::Exponentiate),
+        span,
+    ));
+
+    let (minus, magnitude) = match digits.strip_prefix('-') {
+        Some(rest) => (Some(Span::new(start, start + '⁻'.len_utf8())), rest),
+        None => (None, digits.as_str()),
+    };
+    if magnitude.is_empty() || !magnitude.chars().all(|c| c.is_ascii_digit())
+    {
+        let err = Error::InvalidToken(text, span);
+        tokens.push(SpannedToken::new(Token::Error(err), span));
+        return;
+    }
+
+    let mut number = span;
+    if let Some(minus) = minus {
+        tokens.push(SpannedToken::new(Token::Number(-1.0), minus));
+        tokens.push(SpannedToken::new(
+            Token::Operator(Operator::Multiply),
+            minus,
+        ));
+        number.start = minus.end;
+    }
+    let n = magnitude.parse::<NumericLiteral>().unwrap();
+    tokens.push(SpannedToken::new(Token::Number(n), number));
+}
+
+fn close_radicals(
+    tokens: &mut TokenList,
+    radicals: &mut Vec<usize>,
+    depth: usize,
+) {
+    while radicals.last() == Some(&depth) {
+        radicals.pop();
+        let end = tokens.last().map_or(0, |last| last.span.end);
+        tokens.push(SpannedToken::new(
+            Token::RightParenthesis,
+            Span::new(end, end),
+        ));
+    }
+}
+
+// Wraps up an operand: applies a trailing superscript, closes radicals
+// applied to it and multiplies it with an operand that directly follows.
+fn end_operand(
+    chars: &mut Chars,
+    tokens: &mut TokenList,
+    radicals: &mut Vec<usize>,
+    depth: usize,
+) {
+    exponent(chars, tokens);
+    close_radicals(tokens, radicals, depth);
+
+    match chars.peek() {
+        Some(&(_, c)) if starts_operand(c) => {
+            let end = tokens.last().map_or(0, |last| last.span.end);
+            tokens.push(SpannedToken::new(
+                Token::Operator(Operator::Multiply),
+                Span::new(end, end),
+            ))
+        },
+        _ => {},
+    }
+}
+
+// Appends a term, multiplying it with an operand that ends right before it.
+fn push_term(tokens: &mut TokenList, term: TokenList) {
+    let starts_operand = term.first().is_some_and(|first| {
+        matches!(
+            first.token,
+            Token::Number(_)
+                | Token::Variable(_)
+                | Token::Function(_)
+                | Token::Series(_)
+                | Token::LeftParenthesis
+        )
+    });
+    if ends_operand(tokens) && starts_operand {
+        let at = term[0].span.start;
+        tokens.push(SpannedToken::new(
+            Token::Operator(Operator::Multiply),
+            Span::new(at, at),
+        ));
+    }
+    tokens.extend(term);
+}
+
+// Zero-width spans at the start and end of some tokens.
+fn bounds(tokens: &TokenList, or: Span) -> (Span, Span) {
+    let start = tokens.first().map_or(or.end, |first| first.span.start);
+    let end = tokens.last().map_or(or.end, |last| last.span.end);
+    (Span::new(start, start), Span::new(end, end))
+}
+
+fn parenthesized(tokens: TokenList, or: Span) -> TokenList {
+    let (start, end) = bounds(&tokens, or);
+    let mut grouped = vec![SpannedToken::new(Token::LeftParenthesis, start)];
+    grouped.extend(tokens);
+    grouped.push(SpannedToken::new(Token::RightParenthesis, end));
+    grouped
+}
+
+// `(numerator) / (denominator)`, in parentheses of its own.
+fn divide(
+    numerator: TokenList,
+    slash: Span,
+    denominator: TokenList,
+) -> TokenList {
+    let mut fraction = parenthesized(numerator, slash);
+    fraction.push(SpannedToken::new(Token::Operator(Operator::Divide), slash));
+    fraction.extend(parenthesized(denominator, slash));
+    parenthesized(fraction, slash)
+}
+
+#[cfg(test)]
 mod tests;
@@ -0,0 +1,116 @@
+use crate::{
+    ast::NumericLiteral,
+    error::Error,
+    span::Span,
+    tokens::{SpannedToken, Token},
+    util::consume_while,
+};
+use std::{iter::Peekable, str::CharIndices};
+
+pub type Chars<'a> = Peekable<CharIndices<'a>>;
+
+/// Whether the upcoming characters form the start of a numeric literal.
+pub fn starts_number(chars: &Chars) -> bool {
+    let mut ahead = chars.clone().map(|(_, c)| c);
+    match ahead.next() {
+        Some(c) if c.is_ascii_digit() => true,
+        Some('.') => ahead.next().is_some_and(|c| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+// Once a prefix is seen the literal is an integer in that radix, so `0x` or
+// `0b2` are malformed rather than `0` times a name.
+fn radix_prefix(chars: &Chars) -> Option<u32> {
+    let mut ahead = chars.clone().map(|(_, c)| c);
+    match (ahead.next(), ahead.next()) {
+        (Some('0'), Some('x')) => Some(16),
+        (Some('0'), Some('b')) => Some(2),
+        (Some('0'), Some('o')) => Some(8),
+        _ => None,
+    }
+}
+
+// An exponent is only consumed when digits follow, so that `2E` is still
+// read as `2 * E`.
+fn starts_exponent(chars: &Chars) -> bool {
+    let mut ahead = chars.clone().map(|(_, c)| c);
+    match ahead.next() {
+        Some('e') | Some('E') => match ahead.next() {
+            Some('+') | Some('-') => {
+                ahead.next().is_some_and(|c| c.is_ascii_digit())
+            },
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+// Digit group separators are only allowed between two digits.
+fn valid_separators(digits: &str, radix: u32) -> bool {
+    let chars: Vec<char> = digits.chars().collect();
+    chars.iter().enumerate().all(|(i, &c)| {
+        c != '_'
+            || (i > 0
+                && i + 1 < chars.len()
+                && chars[i - 1].is_digit(radix)
+                && chars[i + 1].is_digit(radix))
+    })
+}
+
+/// Consumes a numeric literal: a decimal with an optional fraction and
+/// exponent, or a `0x`, `0b` or `0o` prefixed integer. Both may contain `_`
+/// digit separators.
+///
+/// Malformed literals such as `1.2.3` are returned as `Token::Error`.
+pub fn consume_number(chars: &mut Chars, offset: usize) -> SpannedToken {
+    let start = offset + chars.peek().map_or(0, |&(i, _)| i);
+    let mut text = String::new();
+
+    let value = if let Some(radix) = radix_prefix(chars) {
+        text.extend(chars.by_ref().take(2).map(|(_, c)| c));
+        let digits = consume_while(chars, |c| c.is_alphanumeric() || c == '_');
+        text.push_str(&digits);
+
+        if !digits.is_empty() && valid_separators(&digits, radix) {
+            u64::from_str_radix(&digits.replace('_', ""), radix)
+                .ok()
+                .map(|n| n as NumericLiteral)
+        }
+        else {
+            None
+        }
+    }
+    else {
+        text = consume_while(chars, |c| {
+            c.is_ascii_digit() || c == '_' || c == '.'
+        });
+        if starts_exponent(chars) {
+            text.extend(chars.by_ref().take(2).map(|(_, c)| c));
+            text.push_str(&consume_while(chars, |c| {
+                c.is_ascii_digit() || c == '_'
+            }));
+        }
+
+        if valid_separators(&text, 10) {
+            text.replace('_', "").parse::<NumericLiteral>().ok()
+        }
+        else {
+            None
+        }
+    };
+
+    // Anything numeric left over, as in `1.2.3` or `1e5.5`, makes the whole
+    // literal invalid.
+    let rest = consume_while(chars, |c| c.is_ascii_digit() || c == '.');
+    let value = value.filter(|_| rest.is_empty());
+    text.push_str(&rest);
+
+    let span = Span::new(start, start + text.len());
+    let token = match value {
+        Some(n) => Token::Number(n),
+        None => Token::Error(Error::InvalidToken(text, span)),
+    };
+    SpannedToken::new(token, span)
+}
@@ -2,10 +2,24 @@
 mod test {
     use crate::{
         ast::Scope,
-        lexer::tokenize,
+        error::Error,
+        lexer,
+        operators::OperatorTable,
+        options::ParserOptions,
         scope,
-        tokens::{Operator, Token},
+        span::Span,
+        tokens::{Operator, Token, TokenList},
     };
+
+    fn tokenize(expr: &str, scope: &Scope) -> Result<TokenList, Error> {
+        lexer::tokenize(
+            expr,
+            scope,
+            &ParserOptions::default(),
+            &OperatorTable::default(),
+        )
+    }
+
     #[test]
     fn lexer_negative_numbers() {
         let tokens = tokenize("x+-1", &Scope::new()).unwrap();
@@ -89,21 +103,56 @@ mod test {
     #[test]
     fn lexer_word_variables() {
         assert_eq!(
-            tokenize("quantity*2", &scope! { "quantity" => 1 }),
-            Ok(vec![
+            tokenize("quantity*2", &scope! { "quantity" => 1 }).unwrap(),
+            vec![
                 Token::Variable("quantity".to_string()),
                 Token::Operator(Operator::Multiply),
                 Token::Number(2.0),
-            ])
+            ]
         );
 
         assert_eq!(
-            tokenize("2quantity", &scope! { "quantity" => 1 }),
-            Ok(vec![
+            tokenize("2quantity", &scope! { "quantity" => 1 }).unwrap(),
+            vec![
                 Token::Number(2.0),
                 Token::Operator(Operator::Multiply),
                 Token::Variable("quantity".to_string()),
-            ])
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_identifiers() {
+        let scope = Scope::new();
+        let var = |name: &str| Token::Variable(name.to_string());
+
+        assert_eq!(tokenize("x1", &scope).unwrap(), vec![var("x1")]);
+        assert_eq!(tokenize("v_0", &scope).unwrap(), vec![var("v_0")]);
+        assert_eq!(
+            tokenize("alpha_max", &scope).unwrap(),
+            vec![var("alpha_max")]
+        );
+        assert_eq!(tokenize("_tmp2", &scope).unwrap(), vec![var("_tmp2")]);
+        assert_eq!(
+            tokenize("2x_1", &scope).unwrap(),
+            vec![
+                Token::Number(2.0),
+                Token::Operator(Operator::Multiply),
+                var("x_1"),
+            ]
+        );
+
+        let tokens = tokenize("x_{ij}+x_{1}", &scope).unwrap();
+        assert_eq!(
+            tokens,
+            vec![var("x_ij"), Token::Operator(Operator::Add), var("x_1")]
+        );
+        assert_eq!(tokens[0].span, Span::new(0, 6));
+        assert_eq!(tokens[2].span, Span::new(7, 12));
+
+        assert_eq!(
+            tokenize("x_{i", &scope),
+            Err(Error::InvalidToken("{".to_string(), Span::new(2, 3)))
         );
     }
 
@@ -203,4 +252,278 @@ mod test {
         ];
         assert_eq!(tokens, expected_tokens)
     }
+
+    #[test]
+    fn token_spans() {
+        let tokens = tokenize("2x >= max(1, -3)", &Scope::new()).unwrap();
+        let spans: Vec<Span> = tokens.iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 1),
+                Span::new(1, 1),
+                Span::new(1, 2),
+                Span::new(3, 5),
+                Span::new(6, 9),
+                Span::new(9, 10),
+                Span::new(10, 11),
+                Span::new(11, 12),
+                Span::new(13, 14),
+                Span::new(13, 14),
+                Span::new(14, 15),
+                Span::new(15, 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_numeric_literals() {
+        let number = |expr| tokenize(expr, &Scope::new()).unwrap();
+
+        assert_eq!(number("1.5e-3"), vec![Token::Number(1.5e-3)]);
+        assert_eq!(number("6.022E23"), vec![Token::Number(6.022e23)]);
+        assert_eq!(number("2e+2"), vec![Token::Number(200.0)]);
+        assert_eq!(number(".5"), vec![Token::Number(0.5)]);
+        assert_eq!(number("0x1F"), vec![Token::Number(31.0)]);
+        assert_eq!(number("0b1010"), vec![Token::Number(10.0)]);
+        assert_eq!(number("0o17"), vec![Token::Number(15.0)]);
+        assert_eq!(number("1_000_000"), vec![Token::Number(1_000_000.0)]);
+        assert_eq!(
+            number("2E"),
+            vec![
+                Token::Number(2.0),
+                Token::Operator(Operator::Multiply),
+                Token::Variable("E".to_string()),
+            ]
+        );
+        assert_eq!(
+            number("2e-x"),
+            vec![
+                Token::Number(2.0),
+                Token::Operator(Operator::Multiply),
+                Token::Variable("e".to_string()),
+                Token::Operator(Operator::Substract),
+                Token::Variable("x".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_invalid_numeric_literals() {
+        let invalid = |expr: &str| {
+            Err(Error::InvalidToken(
+                expr.to_string(),
+                Span::new(0, expr.len()),
+            ))
+        };
+
+        for expr in &["1.2.3", "1e5.5", "1__0", "1_", "0x1G", "0b102"] {
+            assert_eq!(tokenize(expr, &Scope::new()), invalid(expr));
+        }
+        // A radix prefix needs at least one valid digit
+        for expr in &["0x", "0xG", "0b2", "0o", "0o9"] {
+            assert_eq!(tokenize(expr, &Scope::new()), invalid(expr));
+        }
+        assert_eq!(
+            tokenize("0x + 1", &Scope::new()),
+            Err(Error::InvalidToken("0x".to_string(), Span::new(0, 2)))
+        );
+    }
+
+    #[test]
+    fn lexer_unicode_operators() {
+        assert_eq!(
+            tokenize("2×3÷4·5−1", &Scope::new()).unwrap(),
+            vec![
+                Token::Number(2.0),
+                Token::Operator(Operator::Multiply),
+                Token::Number(3.0),
+                Token::Operator(Operator::Divide),
+                Token::Number(4.0),
+                Token::Operator(Operator::Multiply),
+                Token::Number(5.0),
+                Token::Operator(Operator::Substract),
+                Token::Number(1.0),
+            ]
+        );
+        assert_eq!(
+            tokenize("x≤1≠2", &Scope::new()).unwrap(),
+            vec![
+                Token::Variable("x".to_string()),
+                Token::Operator(Operator::IsLessThanOrEqualTo),
+                Token::Number(1.0),
+                Token::Operator(Operator::IsNotEqualTo),
+                Token::Number(2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexer_unicode_symbols() {
+        let tokens = tokenize("2πr²", &Scope::new()).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Number(2.0),
+                Token::Operator(Operator::Multiply),
+                Token::Variable("PI".to_string()),
+                Token::Operator(Operator::Multiply),
+                Token::Variable("r".to_string()),
+                Token::Operator(Operator::Exponentiate),
+                Token::Number(2.0),
+            ]
+        );
+        assert_eq!(tokens[2].span, Span::new(1, 3));
+
+        // A superscript minus is a unary minus, like `-`
+        let tokens = tokenize("x⁻¹", &Scope::new()).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Variable("x".to_string()),
+                Token::Operator(Operator::Exponentiate),
+                Token::Number(-1.0),
+                Token::Operator(Operator::Multiply),
+                Token::Number(1.0),
+            ]
+        );
+        assert_eq!(tokens[3].span, Span::new(1, 4));
+        assert_eq!(tokens[4].span, Span::new(4, 6));
+
+        assert_eq!(
+            tokenize("√x+∛(8)", &Scope::new()).unwrap(),
+            vec![
+                Token::Function("sqrt".to_string()),
+                Token::LeftParenthesis,
+                Token::Variable("x".to_string()),
+                Token::RightParenthesis,
+                Token::Operator(Operator::Add),
+                Token::Function("cbrt".to_string()),
+                Token::LeftParenthesis,
+                Token::Number(8.0),
+                Token::RightParenthesis,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_division_operators() {
+        assert_eq!(
+            tokenize("7 % 2 mod 3 // 4", &Scope::new()).unwrap(),
+            vec![
+                Token::Number(7.0),
+                Token::Operator(Operator::Remainder),
+                Token::Number(2.0),
+                Token::Operator(Operator::Modulo),
+                Token::Number(3.0),
+                Token::Operator(Operator::FloorDivide),
+                Token::Number(4.0),
+            ]
+        );
+
+        let slashes = tokenize("1 / / 2", &Scope::new()).unwrap();
+        assert_eq!(slashes[1], Token::Operator(Operator::Divide));
+        assert_eq!(slashes[1].span, Span::new(2, 3));
+        assert_eq!(slashes[2].span, Span::new(4, 5));
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        assert_eq!(
+            tokenize("1 && 2 || 3 and 4 or 5 xor 6", &Scope::new()).unwrap(),
+            vec![
+                Token::Number(1.0),
+                Token::Operator(Operator::And),
+                Token::Number(2.0),
+                Token::Operator(Operator::Or),
+                Token::Number(3.0),
+                Token::Operator(Operator::And),
+                Token::Number(4.0),
+                Token::Operator(Operator::Or),
+                Token::Number(5.0),
+                Token::Operator(Operator::Xor),
+                Token::Number(6.0),
+            ]
+        );
+        assert_eq!(
+            tokenize("1∧2", &Scope::new()).unwrap()[1].span,
+            Span::new(1, 4)
+        );
+    }
+
+    #[test]
+    fn test_conditional() {
+        assert_eq!(
+            tokenize("x?-1:if(1,2,3)", &scope! { "x" => 1 }).unwrap(),
+            vec![
+                Token::Variable("x".to_string()),
+                Token::QuestionMark,
+                Token::Number(-1.0),
+                Token::Operator(Operator::Multiply),
+                Token::Number(1.0),
+                Token::Colon,
+                Token::Function("if".to_string()),
+                Token::LeftParenthesis,
+                Token::Number(1.0),
+                Token::Comma,
+                Token::Number(2.0),
+                Token::Comma,
+                Token::Number(3.0),
+                Token::RightParenthesis,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_postfix_operators() {
+        assert_eq!(
+            tokenize("!5!! + 3! != 20% % 3", &Scope::new()).unwrap(),
+            vec![
+                Token::Operator(Operator::Not),
+                Token::Number(5.0),
+                Token::Operator(Operator::DoubleFactorial),
+                Token::Operator(Operator::Add),
+                Token::Number(3.0),
+                Token::Operator(Operator::Factorial),
+                Token::Operator(Operator::IsNotEqualTo),
+                Token::Number(20.0),
+                Token::Operator(Operator::Percent),
+                Token::Operator(Operator::Remainder),
+                Token::Number(3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_absolute_value_bars() {
+        let abs = || Token::Function("abs".to_string());
+
+        assert_eq!(
+            tokenize("2*||x|-1|", &scope! { "x" => 1 }).unwrap(),
+            vec![
+                Token::Number(2.0),
+                Token::Operator(Operator::Multiply),
+                abs(),
+                Token::LeftParenthesis,
+                abs(),
+                Token::LeftParenthesis,
+                Token::Variable("x".to_string()),
+                Token::RightParenthesis,
+                Token::Operator(Operator::Substract),
+                Token::Number(1.0),
+                Token::RightParenthesis,
+            ]
+        );
+        assert_eq!(
+            tokenize("1 || 0", &Scope::new()).unwrap()[1],
+            Token::Operator(Operator::Or)
+        );
+
+        for (expr, at) in &[("x|", 1), ("|", 0), ("2 + |", 4)] {
+            assert_eq!(
+                tokenize(expr, &scope! { "x" => 1 }),
+                Err(Error::UnbalancedParentheses(Span::new(*at, at + 1)))
+            );
+        }
+    }
 }
@@ -43,6 +43,11 @@
 //! assert_eq!(Ok(27.0), expression.eval_with(&scope_two));
 //! ```
 //!
+//! When no values exist yet, `compile_declared` compiles from a set of
+//! `Declarations` instead, and `validate` later checks that a scope provides
+//! every name used. The resulting `CompiledExpr` owns its tree, so it can be
+//! cached and shared across threads.
+//!
 //! # Custom Functions
 //!
 //! A lot of effort has been put into making custom functions as easy to write
@@ -64,6 +69,7 @@
 //! # Builtins
 //!
 //! Functions:
+//! - `sin(x)`
 //! - `cos(x)`
 //! - `tan(x)`
 //! - `max(a,b,c,...)`
@@ -76,25 +82,52 @@
 //! - `ln(x)`
 //! - `floor(x)`
 //! - `ceil(x)`
+//! - `if(cond, a, b)`
 //!
 //! Constants:
 //! - PI
 //! - E (Euler's number)
 //! - INFINITY
 //! - NEG_INFINITY
+//!
+//! Unicode symbols pasted from documents work as well: `×`, `·`, `÷`, `−`,
+//! `≤`, `≥`, `≠`, `√`, `∛`, `π`, `∞` and superscript exponents such as `x²`
+//! or `x⁻¹`.
+//!
+//! Greek letters can name variables, either spelled out or as glyphs:
+//! `theta` and `θ` are the same variable.
+//!
+//! Expressions can also be written in AsciiMath, as in `frac(1)(2) sqrt x`,
+//! or in LaTeX, as in `\frac{1}{2} \sqrt{x}`, by setting
+//! `ParserOptions::syntax` to `Syntax::AsciiMath` or `Syntax::Latex`. Both
+//! also read sums and products such as `sum_(i=1)^n i^2`.
 
 mod ast;
 pub(crate) mod constants;
+mod diagnostic;
 mod error;
 mod lexer;
 mod macros;
+mod operators;
+mod options;
 mod parser;
+mod span;
 mod tokens;
 mod util;
 
 pub use crate::{
-    ast::{Evaluate, Scope},
+    ast::{
+        walk_expr, walk_expr_mut, BinaryOp, CompiledExpr, Declarations,
+        Evaluate, Expr, Names, Scope, SeriesOp, UnaryOp, Visitor, VisitorMut,
+    },
     constants::CustomFn,
+    diagnostic::Diagnostic,
     error::Error,
-    parser::{compile, eval},
+    operators::{Associativity, CustomOperator, Fixity},
+    options::{ImplicitMultiplication, ParserOptions, PrintOptions, Syntax},
+    parser::{
+        compile, compile_declared, compile_recovering, eval, Parser,
+        ParserBuilder,
+    },
+    span::Span,
 };
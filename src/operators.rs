@@ -0,0 +1,142 @@
+use crate::{constants::CustomFn, error::Error, tokens::Operator};
+use std::ptr;
+
+/// How operators of equal precedence group: `a - b - c` is `(a - b) - c`
+/// since `-` is left-associative, `a ^ b ^ c` is `a ^ (b ^ c)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fixity {
+    Prefix,
+    Infix,
+    Postfix,
+}
+
+/// An operator defined through `ParserBuilder`.
+#[derive(Clone, Debug)]
+pub struct CustomOperator {
+    pub symbol: String,
+    pub fixity: Fixity,
+    pub precedence: i8,
+    pub associativity: Associativity,
+    pub eval: CustomFn,
+}
+
+impl PartialEq for CustomOperator {
+    fn eq(&self, other: &CustomOperator) -> bool {
+        self.symbol == other.symbol
+            && self.fixity == other.fixity
+            && self.precedence == other.precedence
+            && self.associativity == other.associativity
+            && ptr::fn_addr_eq(self.eval, other.eval)
+    }
+}
+
+/// The user-defined and disabled operators of a `Parser`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OperatorTable {
+    custom: Vec<CustomOperator>,
+    disabled: Vec<Operator>,
+}
+
+// Builtin operators that can be written with more than one character. A
+// custom operator only takes precedence over a longer builtin one.
+const MULTI_CHAR_BUILTINS: &[&str] =
+    &["&&", "||", "//", "<=", ">=", "==", "!=", "!!"];
+
+impl OperatorTable {
+    /// Adds `operator`, replacing any with the same symbol and fixity.
+    pub fn define(&mut self, operator: CustomOperator) -> Result<(), Error> {
+        let symbol = &operator.symbol;
+        // Operands of an operator bind at least one level tighter than it
+        if operator.precedence == i8::MAX {
+            return Err(Error::OperatorPrecedence(symbol.clone()));
+        }
+        if !is_symbol(symbol) {
+            return Err(Error::InvalidOperatorSymbol(symbol.clone()));
+        }
+        if operator.fixity != Fixity::Prefix
+            && self.custom.iter().any(|custom| {
+                custom.symbol == *symbol
+                    && custom.fixity != Fixity::Prefix
+                    && custom.fixity != operator.fixity
+            })
+        {
+            return Err(Error::AmbiguousOperator(symbol.clone()));
+        }
+
+        self.custom.retain(|custom| {
+            custom.symbol != operator.symbol || custom.fixity != operator.fixity
+        });
+        self.custom.push(operator);
+        Ok(())
+    }
+
+    pub fn disable(&mut self, symbol: &str) {
+        self.disabled.extend(Operator::from_symbol(symbol));
+    }
+
+    pub fn is_disabled(&self, operator: &Operator) -> bool {
+        self.disabled.contains(operator)
+    }
+
+    /// The symbol of the first custom operator defined, if any.
+    pub fn custom_symbol(&self) -> Option<&str> {
+        self.custom.first().map(|custom| custom.symbol.as_str())
+    }
+
+    pub fn is_symbol(&self, word: &str) -> bool {
+        self.custom.iter().any(|custom| custom.symbol == word)
+    }
+
+    /// The length of the custom operator symbol at the start of `expr`, if
+    /// any.
+    pub fn match_symbol(&self, expr: &str) -> Option<usize> {
+        let builtin = MULTI_CHAR_BUILTINS
+            .iter()
+            .find(|builtin| expr.starts_with(*builtin))
+            .map_or(0, |builtin| builtin.len());
+
+        self.custom
+            .iter()
+            .map(|custom| custom.symbol.as_str())
+            .filter(|symbol| {
+                !symbol.starts_with(char::is_alphabetic)
+                    && expr.starts_with(symbol)
+            })
+            .map(str::len)
+            .filter(|&len| len >= builtin)
+            .max()
+    }
+
+    /// The definition of `symbol` before an operand.
+    pub fn prefix(&self, symbol: &str) -> Option<&CustomOperator> {
+        self.custom.iter().find(|custom| {
+            custom.symbol == symbol && custom.fixity == Fixity::Prefix
+        })
+    }
+
+    /// The definition of `symbol` after an operand.
+    pub fn infix(&self, symbol: &str) -> Option<&CustomOperator> {
+        self.custom.iter().find(|custom| {
+            custom.symbol == symbol && custom.fixity != Fixity::Prefix
+        })
+    }
+}
+
+// Symbols are either a word like `nand`, or punctuation like `<<` or `±`.
+fn is_symbol(symbol: &str) -> bool {
+    let punctuation = |c: char| {
+        !c.is_alphanumeric()
+            && !c.is_whitespace()
+            && !matches!(c, '(' | ')' | ',' | '.' | '_')
+    };
+
+    !symbol.is_empty()
+        && (symbol.chars().all(char::is_alphabetic)
+            || symbol.chars().all(punctuation))
+}
@@ -0,0 +1,225 @@
+/// Settings controlling how expressions are parsed.
+///
+/// ```
+/// use asciimath::{eval, Error, Parser, ParserOptions, Scope, Span};
+///
+/// let scope = Scope::new();
+/// let lenient = Parser::new(ParserOptions {
+///     strict: false,
+///     ..ParserOptions::default()
+/// });
+///
+/// assert_eq!(Ok(6.0), lenient.eval("2 * 3 #", &scope));
+/// assert_eq!(
+///     Err(Error::InvalidToken("#".to_string(), Span::new(6, 7))),
+///     eval("2 * 3 #", &scope)
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParserOptions {
+    /// Reject characters that are not part of the grammar with an
+    /// `Error::InvalidToken`. When disabled, such characters are skipped.
+    ///
+    /// Enabled by default.
+    pub strict: bool,
+    /// Where a missing `*` is implied, as in `2x`.
+    ///
+    /// `ImplicitMultiplication::Full` by default.
+    pub implicit_multiplication: ImplicitMultiplication,
+    /// Whether an implied `*` binds tighter than `/`, making `1/2x` equal to
+    /// `1/(2x)` rather than `(1/2)x`. It still binds looser than `^`.
+    ///
+    /// Disabled by default.
+    pub implicit_binds_tighter: bool,
+    /// Let a function name without parentheses apply to the term that
+    /// follows it, as in `sin x` or `ln 2x`.
+    ///
+    /// The argument runs up to the first explicit operator binding looser
+    /// than `^`: `sin x^2` is `sin(x^2)` and `ln 2x` is `ln(2x)`, but
+    /// `sin x * 2` is `sin(x) * 2` and `sin x + 1` is `sin(x) + 1`. A
+    /// function name must be separated from a variable by a space, since
+    /// `sinx` is a single word. Like `log(x)`, `log x` is the common
+    /// logarithm `log_10(x)`.
+    ///
+    /// A function name after an operand multiplies it, even across a space,
+    /// and ends the argument of a function before it: `2 sin x` is
+    /// `2 * sin(x)` and `sin x sin y` is `sin(x) * sin(y)`.
+    ///
+    /// Disabled by default.
+    pub function_application: bool,
+    /// The notation expressions are written in.
+    ///
+    /// `Syntax::Plain` by default.
+    pub syntax: Syntax,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            strict: true,
+            implicit_multiplication: ImplicitMultiplication::Full,
+            implicit_binds_tighter: false,
+            function_application: false,
+            syntax: Syntax::Plain,
+        }
+    }
+}
+
+/// Where `ParserOptions` implies a multiplication between two operands.
+///
+/// ```
+/// use asciimath::{
+///     scope, Error, ImplicitMultiplication, Parser, ParserOptions, Span,
+/// };
+///
+/// let parser = |implicit_multiplication| {
+///     Parser::new(ParserOptions {
+///         implicit_multiplication,
+///         ..ParserOptions::default()
+///     })
+/// };
+/// let scope = scope! { "x" => 2, "y" => 3 };
+///
+/// let full = parser(ImplicitMultiplication::Full);
+/// assert_eq!(Ok(6.0), full.eval("xy", &scope));
+///
+/// let numbers = parser(ImplicitMultiplication::NumbersAndParens);
+/// assert_eq!(Ok(12.0), numbers.eval("2x(y)", &scope));
+/// assert_eq!(
+///     Err(Error::UnknownVariable("xy".to_string(), Span::new(0, 2))),
+///     numbers.eval("xy", &scope)
+/// );
+///
+/// let disabled = parser(ImplicitMultiplication::Disabled);
+/// assert_eq!(
+///     Err(Error::ImplicitMultiplication(Span::new(1, 1))),
+///     disabled.eval("2x", &scope)
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImplicitMultiplication {
+    /// Every multiplication must be written out. Leaving one out is an
+    /// `Error::ImplicitMultiplication`.
+    Disabled,
+    /// Only next to a number or a parenthesis: `2x`, `2(x + 1)`, `(a)(b)`.
+    /// A run of letters is always a single variable.
+    NumbersAndParens,
+    /// Additionally splits a run of letters that is not a known variable
+    /// into single-letter variables, so `xy` is `x * y`.
+    Full,
+}
+
+/// The notation `ParserOptions` accepts.
+///
+/// Every syntax compiles to the same tree, so an expression evaluates and
+/// prints the same whichever way it was written.
+///
+/// ```
+/// use asciimath::{Evaluate, Parser, ParserOptions, Scope, Syntax};
+///
+/// let scope = Scope::new();
+/// let parser = |syntax| {
+///     Parser::new(ParserOptions {
+///         syntax,
+///         ..ParserOptions::default()
+///     })
+/// };
+///
+/// let asciimath = parser(Syntax::AsciiMath);
+/// let expr = asciimath.compile("frac(1)(2) + sqrt 16", &scope).unwrap();
+/// assert_eq!(Ok(4.5), expr.eval());
+/// assert_eq!("1 / 2 + sqrt(16)", expr.to_string());
+///
+/// let latex = parser(Syntax::Latex);
+/// let expr = latex.compile(r"\frac{1}{2} + \sqrt{16}", &scope).unwrap();
+/// assert_eq!("1 / 2 + sqrt(16)", expr.to_string());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    /// The syntax described in the crate documentation, as in
+    /// `sqrt(x^2 + 1) / 2`.
+    Plain,
+    /// The evaluable part of the [AsciiMath](http://asciimath.org) markup,
+    /// as in `frac(sqrt(x^2 + 1))(2)` or `sum_(i=1)^n i^2`.
+    ///
+    /// As in AsciiMath, `/` divides the terms directly around it, so
+    /// `1/2x` is `(1/2) x` while `a+b/c+d` is `a + (b/c) + d`. A function
+    /// applies to the term that follows it with its exponent, as in
+    /// `sin x^2`, while a radical only covers the term: `sqrt x^2` is
+    /// `sqrt(x)^2`. A run of letters that is not a known symbol or a
+    /// declared name is a product of single-letter variables. `=` compares
+    /// for equality, `log` without a base is `log_10`, `e` is Euler's number
+    /// unless declared as a variable, and `x_i` is the variable `x_i`.
+    ///
+    /// Presentational markup with no numeric value, such as `text(...)`,
+    /// `vec x`, arrows or integrals, is an `Error::UnsupportedSyntax`.
+    ///
+    /// Disabled operators stay disabled, but custom operators are not
+    /// recognized: `ParserBuilder::build` returns an
+    /// `Error::CustomOperatorSyntax` if both are combined. With
+    /// `ImplicitMultiplication::NumbersAndParens`, a multiplication may only
+    /// be left out next to a number or a group, as in `2x` or `x(y)`; `xy`
+    /// is still split into letters, but is then an
+    /// `Error::ImplicitMultiplication`.
+    AsciiMath,
+    /// LaTeX math, as in `\frac{\sqrt{x^{2} + 1}}{2}` or
+    /// `\sum_{i=1}^{n} i^{2}`. The output of `to_latex` reads back into the
+    /// same tree, except that `a // b` reads back as `floor(a / b)`,
+    /// `log(x)` as `log_10(x)`, `NEG_INFINITY` as `-INFINITY`, and numbers
+    /// such as `1e300` as the product `1 \times 10^{300}`. Conditionals, set
+    /// with `cases`, have no LaTeX input form.
+    ///
+    /// Letters are single-letter variables unless declared, and longer names
+    /// are written `\mathrm{rate}`. As in LaTeX, an unbraced argument of
+    /// `^`, `_`, `\frac` or `\sqrt` is a single digit or letter, so `x^23`
+    /// is `x^2 * 3`. A function without parentheses applies to the product
+    /// after it, as in `\sin 2x`, `\log` without a base is `log_10` and `e`
+    /// is Euler's number unless declared as a variable.
+    ///
+    /// Other commands, such as `\int` or `\vec`, are an
+    /// `Error::UnsupportedSyntax`, as is text like `\text{x}`. `%` starts a
+    /// comment, and `\%` is a percentage or remainder.
+    ///
+    /// Operators are handled as for `Syntax::AsciiMath`: disabling `*` also
+    /// disables `\cdot` and `\times`, and disabling `/` disables `\frac`.
+    /// Under `ImplicitMultiplication::NumbersAndParens` a braced group
+    /// counts as a group, so `2\pi` and `\frac{1}{2}x` are allowed while
+    /// `\pi r` is not.
+    Latex,
+}
+
+/// Settings controlling how expressions are printed back as text.
+///
+/// ```
+/// use asciimath::{compile, scope, PrintOptions};
+///
+/// let expr = compile("2 * (x + 1) ^ 2", &scope! {}).unwrap().into_compiled();
+///
+/// let compact = PrintOptions {
+///     spaced: false,
+///     implicit_multiplication: true,
+/// };
+/// assert_eq!("2(x+1)^2", expr.to_string_with(&compact));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrintOptions {
+    /// Put spaces around binary operators, `?` and `:`, and after commas.
+    /// `^` and word operators like `mod` are printed the same either way.
+    ///
+    /// Enabled by default.
+    pub spaced: bool,
+    /// Leave out `*` where the parser would imply it, as in `2x`,
+    /// `2(x + 1)` or `(a + b)(c + d)`.
+    ///
+    /// Disabled by default.
+    pub implicit_multiplication: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions {
+            spaced: true,
+            implicit_multiplication: false,
+        }
+    }
+}
@@ -1,151 +1,812 @@
 use crate::{
-    ast::{Args, Evaluate, EvaluationResult, Node, Root, Scope},
+    ast::{
+        Args, CompiledExpr, Evaluate, EvaluationResult, Names, Node, Root,
+        Scope,
+    },
+    constants::CustomFn,
     error::Error,
-    lexer::tokenize,
-    tokens::{Operator, Token, TokenList},
+    lexer::{is_implicit, tokenize, tokenize_recovering},
+    operators::{Associativity, CustomOperator, Fixity, OperatorTable},
+    options::{ParserOptions, Syntax},
+    span::Span,
+    tokens::{Operator, SpannedToken, Token, TokenList},
 };
-use std::{collections::VecDeque, string::ToString};
-
-type NodeList = Vec<Node>;
+use std::{fmt, vec};
 
 pub fn eval(expr: &str, scope: &Scope) -> EvaluationResult {
-    parse_tokens(tokenize(expr, scope)?, scope)?.eval()
+    Parser::default().eval(expr, scope)
 }
 
-pub fn compile<'a>(expr: &'a str, scope: &'a Scope) -> Result<Root<'a>, Error> {
-    parse_tokens(tokenize(expr, scope)?, scope)
+pub fn compile<'a>(expr: &str, scope: &'a Scope) -> Result<Root<'a>, Error> {
+    Parser::default().compile(expr, scope)
 }
 
-fn encounter_func(f: String, operands: &mut NodeList) -> Result<(), Error> {
-    let mut args = Args::with_capacity(2);
+/// Compiles `expr` without a `Scope`, knowing only the names in `names`.
+///
+/// The expression can be evaluated with any `Scope` later on, or with none
+/// through `Evaluate::eval`. Since names are not checked while compiling, use
+/// `CompiledExpr::validate` to find those that a scope does not provide.
+///
+/// ```
+/// use asciimath::{compile_declared, scope, Declarations, Evaluate};
+///
+/// let expr = compile_declared("2x + 1", &Declarations::new()).unwrap();
+///
+/// assert_eq!(Ok(7.0), expr.eval_with(&scope! { "x" => 3 }));
+/// assert_eq!(Ok(11.0), expr.eval_with(&scope! { "x" => 5 }));
+/// ```
+pub fn compile_declared(
+    expr: &str,
+    names: &dyn Names,
+) -> Result<CompiledExpr, Error> {
+    Parser::default().compile_declared(expr, names)
+}
 
-    // ASSUMPTION: at least one argument per function
-    args.push_front(
-        operands
-            .pop()
-            .ok_or(Error::NotEnoughFunctionParams(f.clone()))?,
-    );
+/// Compiles as much of `expr` as possible, collecting every error instead of
+/// stopping at the first one.
+///
+/// Parts of the expression that could not be parsed are left as placeholders
+/// in the returned tree, and the whole tree evaluates to the first error
+/// unless the list of errors is empty.
+///
+/// ```
+/// use asciimath::{compile_recovering, Scope};
+///
+/// let scope = Scope::new();
+/// let (_, errors) = compile_recovering("max(1,,2) + (3 * 4", &scope);
+///
+/// assert_eq!(errors.len(), 2);
+/// ```
+pub fn compile_recovering<'a>(
+    expr: &str,
+    scope: &'a Scope,
+) -> (Root<'a>, Vec<Error>) {
+    Parser::default().compile_recovering(expr, scope)
+}
+
+/// Compiles expressions with a given set of `ParserOptions`.
+///
+/// The free functions `compile`, `compile_recovering` and `eval` use the
+/// default options.
+#[derive(Clone, Debug, Default)]
+pub struct Parser {
+    options: ParserOptions,
+    operators: OperatorTable,
+}
 
-    while let Some(last) = operands.pop() {
-        if last.token != Token::Comma {
-            operands.push(last);
-            break;
+impl Parser {
+    pub fn new(options: ParserOptions) -> Self {
+        Parser {
+            options,
+            operators: OperatorTable::default(),
         }
-        else {
-            args.push_front(
-                operands
-                    .pop()
-                    .ok_or(Error::FunctionSyntaxError(f.clone()))?,
-            );
+    }
+
+    /// Starts configuring a parser with custom operators.
+    pub fn builder() -> ParserBuilder {
+        ParserBuilder::default()
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    pub fn eval(&self, expr: &str, scope: &Scope) -> EvaluationResult {
+        self.compile(expr, scope)?.eval()
+    }
+
+    pub fn compile<'a>(
+        &self,
+        expr: &str,
+        scope: &'a Scope,
+    ) -> Result<Root<'a>, Error> {
+        let node = self.parse(expr, scope)?;
+        Ok(Root {
+            expr: CompiledExpr { node },
+            scope,
+        })
+    }
+
+    /// See `compile_declared`.
+    pub fn compile_declared(
+        &self,
+        expr: &str,
+        names: &dyn Names,
+    ) -> Result<CompiledExpr, Error> {
+        let node = self.parse(expr, names)?;
+        Ok(CompiledExpr { node })
+    }
+
+    /// See `compile_recovering`.
+    pub fn compile_recovering<'a>(
+        &self,
+        expr: &str,
+        scope: &'a Scope,
+    ) -> (Root<'a>, Vec<Error>) {
+        let span = Span::new(0, expr.len());
+        let tokens =
+            tokenize_recovering(expr, scope, &self.options, &self.operators);
+        let mut parser = Pratt::new(expr, self, tokens, true);
+        let mut node = parser
+            .parse(span)
+            .unwrap_or_else(|err| Node::new(Token::Error(err), None, span));
+
+        // A placeholder in a branch that is never taken, as in `1 ? 2`, would
+        // not fail the evaluation on its own
+        let errors = parser.errors.unwrap_or_default();
+        if let Some(err) = errors.first() {
+            node = wrapped(node, err.clone());
         }
+        let expr = CompiledExpr { node };
+        (Root { expr, scope }, errors)
     }
 
-    operands.push(Node::new(Token::Function(f), Some(args)));
-    Ok(())
+    fn parse(&self, expr: &str, names: &dyn Names) -> Result<Node, Error> {
+        let span = Span::new(0, expr.len());
+        let tokens = tokenize(expr, names, &self.options, &self.operators)?;
+        Pratt::new(expr, self, tokens, false).parse(span)
+    }
 }
 
-fn right_paren(
-    operators: &mut TokenList,
-    operands: &mut NodeList,
-) -> Result<(), Error> {
-    while let Some(top) = operators.pop() {
-        match top {
-            Token::LeftParenthesis => match operators.last() {
-                Some(Token::Function(_)) => {},
-                _ => break,
-            },
-            Token::Function(f) => encounter_func(f, operands)?,
-            Token::Operator(op) => add_operator(op, operands)?,
-            _ => {},
+/// Builds a `Parser` with user-defined operators, or with some of the builtin
+/// ones disabled.
+///
+/// Operators are written either as punctuation like `<<` or `±`, or as a
+/// word like `nand`. Defining a builtin symbol overrides it, and a symbol can
+/// be both a prefix and an infix or postfix operator, like `-`. Each
+/// operator is evaluated by a callback receiving its operands.
+///
+/// Precedences are compared with those of the builtin operators:
+///
+/// | Precedence | Operators                         |
+/// |------------|-----------------------------------|
+/// | 0          | `? :`                             |
+/// | 10         | `\|\|`                             |
+/// | 20         | `xor`                             |
+/// | 30         | `&&`                              |
+/// | 40         | `<` `<=` `>` `>=` `==` `!=`       |
+/// | 50         | `+` `-` `!` (prefix)              |
+/// | 60         | `*` `/` `%` `mod` `//`            |
+/// | 70         | `^`                               |
+/// | 80         | `!` `!!` `%` (postfix)            |
+///
+/// ```
+/// use asciimath::{Associativity, CustomFn, Error, Parser, Scope, Span};
+///
+/// let shl: CustomFn = |args| Ok(args[0] * 2f64.powf(args[1]));
+/// let parser = Parser::builder()
+///     .infix("<<", 45, Associativity::Left, shl)
+///     .disable("^")
+///     .build()?;
+///
+/// let scope = Scope::new();
+/// assert_eq!(Ok(12.0), parser.eval("1 + 2 << 2", &scope));
+/// assert_eq!(
+///     Err(Error::InvalidToken("^".to_string(), Span::new(1, 2))),
+///     parser.eval("2^3", &scope)
+/// );
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ParserBuilder {
+    options: ParserOptions,
+    operators: OperatorTable,
+    // Definitions in order, checked by `build`
+    custom: Vec<CustomOperator>,
+}
+
+impl ParserBuilder {
+    pub fn options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Defines a binary operator.
+    pub fn infix(
+        self,
+        symbol: &str,
+        precedence: i8,
+        associativity: Associativity,
+        eval: CustomFn,
+    ) -> Self {
+        self.define(symbol, Fixity::Infix, precedence, associativity, eval)
+    }
+
+    /// Defines an operator written before its operand. Its operand includes
+    /// every operator with a higher precedence.
+    pub fn prefix(self, symbol: &str, precedence: i8, eval: CustomFn) -> Self {
+        let associativity = Associativity::Right;
+        self.define(symbol, Fixity::Prefix, precedence, associativity, eval)
+    }
+
+    /// Defines an operator written after its operand.
+    pub fn postfix(self, symbol: &str, precedence: i8, eval: CustomFn) -> Self {
+        let associativity = Associativity::Left;
+        self.define(symbol, Fixity::Postfix, precedence, associativity, eval)
+    }
+
+    /// Turns off the builtin operators written as `symbol`, along with their
+    /// other spellings: `disable("&&")` also disables `and`. Using them is an
+    /// `Error::InvalidToken`, in every syntax.
+    pub fn disable(mut self, symbol: &str) -> Self {
+        self.operators.disable(symbol);
+        self
+    }
+
+    /// Checks the operators defined, in order. Returns an
+    /// `Error::InvalidOperatorSymbol` for a symbol that is neither a word
+    /// nor punctuation, an `Error::AmbiguousOperator` for a symbol defined as
+    /// both an infix and a postfix operator, and an
+    /// `Error::OperatorPrecedence` for a precedence of `i8::MAX`.
+    ///
+    /// Returns an `Error::CustomOperatorSyntax` if operators are defined and
+    /// the options select a syntax other than `Syntax::Plain`, which is the
+    /// only one that reads them.
+    pub fn build(mut self) -> Result<Parser, Error> {
+        for operator in self.custom {
+            self.operators.define(operator)?;
         }
+        if self.options.syntax != Syntax::Plain {
+            if let Some(symbol) = self.operators.custom_symbol() {
+                return Err(Error::CustomOperatorSyntax(symbol.to_string()));
+            }
+        }
+        Ok(Parser {
+            options: self.options,
+            operators: self.operators,
+        })
+    }
+
+    fn define(
+        mut self,
+        symbol: &str,
+        fixity: Fixity,
+        precedence: i8,
+        associativity: Associativity,
+        eval: CustomFn,
+    ) -> Self {
+        self.custom.push(CustomOperator {
+            symbol: symbol.to_string(),
+            fixity,
+            precedence,
+            associativity,
+            eval,
+        });
+        self
     }
-    Ok(())
 }
 
-fn add_operator(
-    operator: Operator,
-    operands: &mut NodeList,
-) -> Result<(), Error> {
-    let num_operands = operator.num_operands();
+/// Parses a token list by precedence climbing: every operand is parsed
+/// together with the operators that bind tighter than the operator before it.
+struct Pratt<'e> {
+    expr: &'e str,
+    options: &'e ParserOptions,
+    operators: &'e OperatorTable,
+    tokens: vec::IntoIter<SpannedToken>,
+    // Zero-width span at the end of the expression
+    end: Span,
+    // Number of parentheses currently open
+    depth: usize,
+    // Collected errors when recovering
+    errors: Option<Vec<Error>>,
+    // The first stray token skipped before an operand, which fails the whole
+    // expression
+    stray: Option<Error>,
+}
 
-    let mut args: VecDeque<Node> =
-        VecDeque::with_capacity(num_operands as usize);
+impl<'e> Pratt<'e> {
+    fn new(
+        expr: &'e str,
+        parser: &'e Parser,
+        tokens: TokenList,
+        recovering: bool,
+    ) -> Self {
+        Pratt {
+            expr,
+            options: &parser.options,
+            operators: &parser.operators,
+            tokens: tokens.into_iter(),
+            end: Span::new(expr.len(), expr.len()),
+            depth: 0,
+            errors: if recovering { Some(Vec::new()) } else { None },
+            stray: None,
+        }
+    }
 
-    for _ in 0..num_operands {
-        args.push_front(
-            operands
-                .pop()
-                .ok_or(Error::MissingOperands(operator.to_string()))?,
-        );
+    /// Bails out with `err`, or records it and carries on when recovering.
+    fn fail(&mut self, err: Error) -> Result<(), Error> {
+        match self.errors {
+            Some(ref mut errors) => {
+                errors.push(err);
+                Ok(())
+            },
+            None => Err(err),
+        }
     }
-    operands.push(Node::new(Token::Operator(operator), Some(args)));
-    Ok(())
-}
 
-fn encounter_operator(
-    cur_operator: Operator,
-    operators: &mut TokenList,
-    operands: &mut NodeList,
-) -> Result<(), Error> {
-    while let Some(top) = operators.pop() {
-        match top {
-            Token::Operator(top_operator) => {
-                if top_operator > cur_operator
-                    || (top_operator == cur_operator
-                        && !cur_operator.is_right_associative())
-                {
-                    add_operator(top_operator, operands)?
-                }
-                else {
-                    operators.push(Token::Operator(top_operator));
-                    break;
+    /// Like `fail`, but wraps `node` in a placeholder for `err`, so that the
+    /// part that failed evaluates to its error.
+    fn wrap(&mut self, node: Node, err: Error) -> Result<Node, Error> {
+        self.fail(err.clone())?;
+        Ok(wrapped(node, err))
+    }
+
+    /// Like `fail`, but returns a placeholder node to stand in for whatever
+    /// was missing.
+    fn recover(&mut self, err: Error) -> Result<Node, Error> {
+        let span = err.span();
+        self.fail(err.clone())?;
+        Ok(Node::new(Token::Error(err), None, span))
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        self.tokens.as_slice().first().map(|next| &next.token)
+    }
+
+    fn peek_span(&mut self) -> Span {
+        self.tokens.as_slice().first().map_or(self.end, |next| next.span)
+    }
+
+    // The error for a token that cannot appear where it was found.
+    fn unexpected(&self, token: &Token, span: Span) -> Error {
+        match token {
+            Token::RightParenthesis => Error::UnbalancedParentheses(span),
+            Token::Colon => Error::InvalidToken(":".to_string(), span),
+            _ => Error::UnexpectedToken(
+                self.expr
+                    .get(span.start..span.end)
+                    .unwrap_or("")
+                    .to_string(),
+                span,
+            ),
+        }
+    }
+
+    fn parse(&mut self, expr_span: Span) -> Result<Node, Error> {
+        if self.tokens.as_slice().is_empty() {
+            return self.recover(Error::EmptyExpression(expr_span));
+        }
+
+        let mut node = self.expression(0, None)?;
+        while let Some(SpannedToken { token, span }) = self.tokens.next() {
+            node = self.wrap(node, self.unexpected(&token, span))?;
+            node = self.infix(node, 0)?;
+        }
+        Ok(match self.stray.take() {
+            Some(err) => wrapped(node, err),
+            None => node,
+        })
+    }
+
+    /// Parses an operand and every operator binding at least as tightly as
+    /// `min`. `after` is the operator the operand belongs to, if any.
+    fn expression(
+        &mut self,
+        min: i8,
+        after: Option<(Op, Span)>,
+    ) -> Result<Node, Error> {
+        let lhs = self.primary(after)?;
+        self.infix(lhs, min)
+    }
+
+    fn missing(&mut self, after: Option<(Op, Span)>) -> Result<Node, Error> {
+        let err = match after {
+            Some((operator, span)) => {
+                Error::MissingOperands(operator.to_string(), span)
+            },
+            None => Error::EmptyExpression(Span::new(
+                self.peek_span().start,
+                self.peek_span().start,
+            )),
+        };
+        self.recover(err)
+    }
+
+    fn primary(&mut self, after: Option<(Op, Span)>) -> Result<Node, Error> {
+        let span = self.peek_span();
+        let depth = self.depth;
+        let operators = self.operators;
+        match self.peek() {
+            Some(Token::Operator(operator)) if !operator.is_prefix() => {
+                let operator = Op::Builtin(*operator);
+                self.missing(after.or(Some((operator, span))))
+            },
+            Some(Token::Symbol(symbol))
+                if operators.prefix(symbol).is_none() =>
+            {
+                let operator = operators.infix(symbol).cloned();
+                let operator =
+                    operator.map(|custom| (Op::Custom(custom), span));
+                self.missing(after.or(operator))
+            },
+            Some(Token::QuestionMark) => {
+                let operator = Op::Builtin(Operator::Conditional);
+                self.missing(after.or(Some((operator, span))))
+            },
+            Some(Token::Colon) if after.is_none() => {
+                self.tokens.next();
+                self.skip(Error::InvalidToken(":".to_string(), span))?;
+                self.primary(after)
+            },
+            Some(Token::RightParenthesis) if depth == 0 => {
+                self.tokens.next();
+                self.skip(Error::UnbalancedParentheses(span))?;
+                self.primary(after)
+            },
+            Some(Token::Comma)
+            | Some(Token::Colon)
+            | Some(Token::RightParenthesis)
+            | None => self.missing(after),
+            Some(_) => {
+                let SpannedToken { token, span } = self.tokens.next().unwrap();
+                match token {
+                    Token::Number(n) if n == -1.0 && self.negates(span) => {
+                        self.negation(span)
+                    },
+                    Token::Operator(operator) => {
+                        self.prefix(Op::Builtin(operator), span)
+                    },
+                    Token::Symbol(symbol) => {
+                        let custom = self.operators.prefix(&symbol).cloned();
+                        self.prefix(Op::Custom(custom.unwrap()), span)
+                    },
+                    Token::LeftParenthesis => self.group(span),
+                    Token::Function(f) => self.function(f, span),
+                    Token::Series(op) => {
+                        self.call(Token::Series(op), op.name().into(), span)
+                    },
+                    Token::Error(err) => self.recover(err),
+                    token => Ok(Node::new(token, None, span)),
                 }
             },
-            Token::Function(f) => encounter_func(f, operands)?,
+        }
+    }
+
+    // Records a stray token before an operand. The operand may be a branch
+    // that is never evaluated, so the error fails the whole expression.
+    fn skip(&mut self, err: Error) -> Result<(), Error> {
+        self.fail(err.clone())?;
+        self.stray.get_or_insert(err);
+        Ok(())
+    }
+
+    fn prefix(&mut self, operator: Op, span: Span) -> Result<Node, Error> {
+        let min = operator.precedence() + 1;
+        let operand = self.expression(min, Some((operator.clone(), span)))?;
+        Ok(node(operator, span, vec![operand]))
+    }
+
+    // The lexer turns a unary minus into `-1 *`, with both tokens spanning
+    // the `-`.
+    fn negates(&mut self, span: Span) -> bool {
+        self.tokens.as_slice().first().is_some_and(|next| {
+            next.span == span
+                && next.token == Token::Operator(Operator::Multiply)
+        })
+    }
+
+    // Only exponents and postfix operators bind tighter than a unary minus,
+    // so that `8/-2` is `8 / (-2)`.
+    fn negation(&mut self, span: Span) -> Result<Node, Error> {
+        self.tokens.next();
+        let min = Operator::Exponentiate.get_precedence();
+        let minus = Op::Builtin(Operator::Substract);
+        let operand = self.expression(min, Some((minus, span)))?;
+        let minus_one = Node::new(Token::Number(-1.0), None, span);
+        let multiply = Op::Builtin(Operator::Multiply);
+        Ok(node(multiply, span, vec![minus_one, operand]))
+    }
+
+    // Applies the operators following `lhs` that bind at least as tightly as
+    // `min`.
+    fn infix(&mut self, mut lhs: Node, min: i8) -> Result<Node, Error> {
+        loop {
+            let span = self.peek_span();
+            lhs = match self.peek() {
+                Some(Token::Operator(_)) | Some(Token::Symbol(_)) => {
+                    let operator = match self.infix_operator() {
+                        Some(operator) if operator.precedence() >= min => {
+                            operator
+                        },
+                        _ => return Ok(lhs),
+                    };
+                    self.tokens.next();
+                    if operator.is_postfix() {
+                        node(operator, span, vec![lhs])
+                    }
+                    else {
+                        let min = operator.right_binding();
+                        let after = Some((operator.clone(), span));
+                        let rhs = self.expression(min, after)?;
+                        node(operator, span, vec![lhs, rhs])
+                    }
+                },
+                Some(Token::QuestionMark) if min <= 0 => {
+                    self.tokens.next();
+                    self.conditional(lhs, span)?
+                },
+                // Stands in for the operator that failed to lex
+                Some(Token::Error(err)) => {
+                    let err = err.clone();
+                    self.tokens.next();
+                    self.fail(err.clone())?;
+
+                    let mut args = Args::from([lhs]);
+                    if self.peek().is_some_and(starts_operand) {
+                        args.push_back(self.primary(None)?);
+                    }
+                    let span =
+                        args.iter().fold(span, |span, arg| span.to(arg.span));
+                    Node::new(Token::Error(err), Some(args), span)
+                },
+                _ => return Ok(lhs),
+            }
+        }
+    }
+
+    // The next operator, if it can follow an operand.
+    fn infix_operator(&mut self) -> Option<Op> {
+        let operators = self.operators;
+        let tighter = self.options.implicit_binds_tighter;
+        if self.tokens.as_slice().first().is_some_and(is_implicit) {
+            return Some(Op::Implicit { tighter });
+        }
+
+        match self.peek()? {
+            Token::Operator(operator) if !operator.is_prefix() => {
+                Some(Op::Builtin(*operator))
+            },
+            Token::Symbol(symbol) => {
+                operators.infix(symbol).cloned().map(Op::Custom)
+            },
+            _ => None,
+        }
+    }
+
+    fn conditional(&mut self, cond: Node, span: Span) -> Result<Node, Error> {
+        let conditional = Op::Builtin(Operator::Conditional);
+        let after = Some((conditional.clone(), span));
+        let then = self.expression(0, after.clone())?;
+        let otherwise = if self.peek() == Some(&Token::Colon) {
+            self.tokens.next();
+            self.expression(0, after)?
+        }
+        else {
+            self.recover(Error::IncompleteConditional(span))?
+        };
+        Ok(node(conditional, span, vec![cond, then, otherwise]))
+    }
+
+    fn group(&mut self, open: Span) -> Result<Node, Error> {
+        match self.peek() {
+            None => return self.recover(Error::UnbalancedParentheses(open)),
+            Some(Token::RightParenthesis) => {
+                let close = self.tokens.next().unwrap().span;
+                return self.recover(Error::EmptyExpression(open.to(close)));
+            },
+            _ => {},
+        }
+
+        self.depth += 1;
+        let mut node = self.expression(0, None)?;
+        loop {
+            match self.tokens.next() {
+                Some(SpannedToken {
+                    token: Token::RightParenthesis,
+                    ..
+                }) => break,
+                Some(SpannedToken { token, span }) => {
+                    node = self.wrap(node, self.unexpected(&token, span))?;
+                    node = self.infix(node, 0)?;
+                },
+                None => {
+                    node = self.wrap(node, Error::UnbalancedParentheses(open))?;
+                    break;
+                },
+            }
+        }
+        self.depth -= 1;
+        Ok(node)
+    }
+
+    fn function(&mut self, f: String, span: Span) -> Result<Node, Error> {
+        if self.options.function_application
+            && self.peek() != Some(&Token::LeftParenthesis)
+        {
+            return self.application(f, span);
+        }
+        self.call(Token::Function(f.clone()), f, span)
+    }
+
+    // The parenthesized arguments of a function or series, whose name `f`
+    // is used in errors.
+    fn call(
+        &mut self,
+        callee: Token,
+        f: String,
+        span: Span,
+    ) -> Result<Node, Error> {
+        let open = match self.tokens.next() {
+            Some(SpannedToken {
+                token: Token::LeftParenthesis,
+                span,
+            }) => span,
             _ => {
-                operators.push(top);
-                break;
+                return self.recover(Error::NotEnoughFunctionParams(
+                    f,
+                    span.to(self.end),
+                ))
             },
+        };
+        let mut args = Args::with_capacity(2);
+
+        if self.peek() == Some(&Token::RightParenthesis) {
+            let close = self.tokens.next().unwrap().span;
+            let err = Error::NotEnoughFunctionParams(f.clone(), span.to(close));
+            args.push_back(self.recover(err)?);
+            return Ok(Node::new(callee, Some(args), span.to(close)));
         }
+
+        self.depth += 1;
+        let mut last_comma = open;
+        let mut failed = None;
+        let close = loop {
+            let arg = match self.peek() {
+                Some(Token::Comma) => {
+                    let err =
+                        Error::FunctionSyntaxError(f.clone(), self.peek_span());
+                    self.recover(err)?
+                },
+                Some(Token::RightParenthesis) | None => self.recover(
+                    Error::FunctionSyntaxError(f.clone(), last_comma),
+                )?,
+                Some(_) => self.expression(0, None)?,
+            };
+            args.push_back(arg);
+
+            match self.peek() {
+                Some(Token::Comma) => {
+                    last_comma = self.tokens.next().unwrap().span;
+                },
+                Some(Token::RightParenthesis) => {
+                    break self.tokens.next().unwrap().span;
+                },
+                None => {
+                    let err = Error::UnbalancedParentheses(open);
+                    self.fail(err.clone())?;
+                    failed.get_or_insert(err);
+                    break self.end;
+                },
+                // Another argument without a comma before it
+                Some(_) => {
+                    let err =
+                        Error::FunctionSyntaxError(f.clone(), self.peek_span());
+                    self.fail(err.clone())?;
+                    failed.get_or_insert(err);
+                },
+            }
+        };
+        self.depth -= 1;
+
+        let call = Node::new(callee, Some(args), span.to(close));
+        Ok(match failed {
+            Some(err) => wrapped(call, err),
+            None => call,
+        })
     }
 
-    operators.push(Token::Operator(cur_operator));
-    Ok(())
+    // A function applied without parentheses, as in `ln 2x`. Its argument
+    // takes exponents, postfix operators and implicit multiplications up to
+    // the next function, so `sin 2x^2` is `sin(2 * x^2)` and `sin x cos x`
+    // is `sin(x) * cos(x)`.
+    fn application(&mut self, f: String, span: Span) -> Result<Node, Error> {
+        if !self.peek().is_some_and(starts_operand) {
+            return self.recover(Error::NotEnoughFunctionParams(f, span));
+        }
+
+        let min = Operator::Exponentiate.get_precedence();
+        let mut arg = self.expression(min, None)?;
+        while let [implicit, next, ..] = self.tokens.as_slice() {
+            let function =
+                matches!(next.token, Token::Function(_) | Token::Series(_));
+            if !is_implicit(implicit) || function {
+                break;
+            }
+            let at = self.tokens.next().unwrap().span;
+            let implicit = Op::Implicit {
+                tighter: self.options.implicit_binds_tighter,
+            };
+            let factor = self.expression(min, Some((implicit.clone(), at)))?;
+            arg = node(implicit, at, vec![arg, factor]);
+        }
+
+        let span = span.to(arg.span);
+        Ok(Node::new(Token::Function(f), Some(Args::from([arg])), span))
+    }
 }
 
-fn parse_tokens(tokens: TokenList, scope: &Scope) -> Result<Root, Error> {
-    let mut operators: TokenList = Vec::new();
-    let mut operands: NodeList = Vec::new();
+// A placeholder for `err` around the part of the tree it affects, unless that
+// part already failed with an earlier error.
+fn wrapped(node: Node, err: Error) -> Node {
+    if let Token::Error(_) = node.token {
+        return node;
+    }
+    let span = node.span.to(err.span());
+    Node::new(Token::Error(err), Some(Args::from([node])), span)
+}
 
-    for token in tokens {
-        match token {
-            Token::Number(num) => operands.push(Node {
-                token: Token::Number(num),
-                args: None,
-            }),
-            Token::Variable(var) => operands.push(Node {
-                token: Token::Variable(var),
-                args: None,
-            }),
-            Token::RightParenthesis => {
-                right_paren(&mut operators, &mut operands)?
+fn starts_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Number(_)
+            | Token::Variable(_)
+            | Token::Function(_)
+            | Token::Series(_)
+            | Token::LeftParenthesis
+            | Token::Error(_)
+    )
+}
+
+// A builtin or user-defined operator, as the parser sees it.
+#[derive(Clone)]
+enum Op {
+    Builtin(Operator),
+    Custom(CustomOperator),
+    // A multiplication implied by two adjacent operands
+    Implicit { tighter: bool },
+}
+
+impl Op {
+    fn precedence(&self) -> i8 {
+        match self {
+            Op::Builtin(operator) => operator.get_precedence(),
+            Op::Custom(custom) => custom.precedence,
+            Op::Implicit { tighter: false } => {
+                Operator::Multiply.get_precedence()
             },
-            Token::LeftParenthesis => operators.push(token),
-            Token::Operator(op1) => {
-                encounter_operator(op1, &mut operators, &mut operands)?;
+            // Between `/` and `^`
+            Op::Implicit { tighter: true } => {
+                Operator::Multiply.get_precedence() + 5
             },
-            Token::Function(f) => operators.push(Token::Function(f)),
-            Token::Comma => operands.push(Node::new(token, None)),
+        }
+    }
+
+    fn is_postfix(&self) -> bool {
+        match self {
+            Op::Builtin(operator) => operator.is_postfix(),
+            Op::Custom(custom) => custom.fixity == Fixity::Postfix,
+            Op::Implicit { .. } => false,
+        }
+    }
+
+    // The minimum precedence of the operators within the right operand.
+    fn right_binding(&self) -> i8 {
+        let right = match self {
+            Op::Builtin(operator) => operator.is_right_associative(),
+            Op::Custom(custom) => custom.associativity == Associativity::Right,
+            Op::Implicit { .. } => false,
         };
+        self.precedence() + if right { 0 } else { 1 }
     }
+}
 
-    while let Some(Token::Operator(operator)) = operators.pop() {
-        add_operator(operator, &mut operands)?
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Op::Builtin(operator) => write!(f, "{}", operator),
+            Op::Custom(custom) => write!(f, "{}", custom.symbol),
+            Op::Implicit { .. } => write!(f, "{}", Operator::Multiply),
+        }
     }
+}
 
-    // TODO: revisit this when the final output can also be a string
-    operands.pop().map_or_else(
-        || Err(Error::EmptyExpression),
-        |node| Ok(Root { node, scope }),
-    )
+// An operator node, spanning the operator and all of its operands.
+fn node(operator: Op, span: Span, args: Vec<Node>) -> Node {
+    let span = args.iter().fold(span, |span, arg| span.to(arg.span));
+    let token = match operator {
+        Op::Builtin(operator) => Token::Operator(operator),
+        Op::Custom(custom) => Token::CustomOperator(custom),
+        Op::Implicit { .. } => Token::Operator(Operator::Multiply),
+    };
+    Node::new(token, Some(args.into()), span)
 }
@@ -1,9 +1,14 @@
-use crate::ast::NumericLiteral;
+use crate::{
+    ast::{NumericLiteral, SeriesOp},
+    error::Error,
+    operators::CustomOperator,
+    span::Span,
+};
 use std::{cmp, fmt};
 
-pub type TokenList = Vec<Token>;
+pub type TokenList = Vec<SpannedToken>;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Token {
     Operator(Operator),
     Number(NumericLiteral),
@@ -11,14 +16,48 @@ pub enum Token {
     LeftParenthesis,
     RightParenthesis,
     Comma,
+    /// A user-defined operator, as written.
+    Symbol(String),
+    /// A user-defined operator, resolved by the parser.
+    CustomOperator(CustomOperator),
+    QuestionMark,
+    Colon,
     Function(String),
+    /// A sum or product, called like a function with the index, the bounds
+    /// and the body. Only the AsciiMath and LaTeX front-ends produce it.
+    Series(SeriesOp),
+    /// Placeholder for input that failed to parse, only produced when
+    /// recovering from errors. Evaluating it yields the error.
+    Error(Error),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+impl SpannedToken {
+    pub fn new(token: Token, span: Span) -> Self {
+        Self { token, span }
+    }
+}
+
+impl cmp::PartialEq<Token> for SpannedToken {
+    fn eq(&self, other: &Token) -> bool {
+        self.token == *other
+    }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Operator {
     Add,
     Substract,
     Multiply,
     Divide,
+    Remainder,
+    Modulo,
+    FloorDivide,
     Exponentiate,
     IsGreaterThan,
     IsLessThan,
@@ -27,6 +66,14 @@ pub enum Operator {
     IsEqualTo,
     IsNotEqualTo,
     Not,
+    Factorial,
+    DoubleFactorial,
+    Percent,
+    And,
+    Or,
+    Xor,
+    /// `cond ? a : b`, formed by the parser once the `:` is found.
+    Conditional,
 }
 
 impl fmt::Display for Operator {
@@ -39,6 +86,9 @@ impl fmt::Display for Operator {
                 Operator::Substract => "-",
                 Operator::Multiply => "*",
                 Operator::Divide => "/",
+                Operator::Remainder => "%",
+                Operator::Modulo => "mod",
+                Operator::FloorDivide => "//",
                 Operator::Exponentiate => "^",
                 Operator::IsGreaterThan => ">",
                 Operator::IsLessThan => "<",
@@ -47,6 +97,13 @@ impl fmt::Display for Operator {
                 Operator::IsEqualTo => "==",
                 Operator::IsNotEqualTo => "!=",
                 Operator::Not => "!",
+                Operator::Factorial => "!",
+                Operator::DoubleFactorial => "!!",
+                Operator::Percent => "%",
+                Operator::And => "&&",
+                Operator::Or => "||",
+                Operator::Xor => "xor",
+                Operator::Conditional => "?:",
             }
         )
     }
@@ -58,43 +115,115 @@ impl fmt::Debug for Operator {
     }
 }
 
-impl cmp::PartialEq for Operator {
-    fn eq(&self, other: &Operator) -> bool {
-        self.get_precedence() == other.get_precedence()
+const OPERATORS: [Operator; 22] = [
+    Operator::Add,
+    Operator::Substract,
+    Operator::Multiply,
+    Operator::Divide,
+    Operator::Remainder,
+    Operator::Modulo,
+    Operator::FloorDivide,
+    Operator::Exponentiate,
+    Operator::IsGreaterThan,
+    Operator::IsLessThan,
+    Operator::IsGreaterThanOrEqualTo,
+    Operator::IsLessThanOrEqualTo,
+    Operator::IsEqualTo,
+    Operator::IsNotEqualTo,
+    Operator::Not,
+    Operator::Factorial,
+    Operator::DoubleFactorial,
+    Operator::Percent,
+    Operator::And,
+    Operator::Or,
+    Operator::Xor,
+    Operator::Conditional,
+];
+
+impl Operator {
+    /// The builtin operators written as `symbol`, including alternative
+    /// spellings such as `and` for `&&`.
+    pub fn from_symbol(symbol: &str) -> Vec<Operator> {
+        OPERATORS
+            .iter()
+            .copied()
+            .filter(|operator| {
+                operator.to_string() == symbol
+                    || operator.aliases().contains(&symbol)
+            })
+            .collect()
     }
-}
-impl cmp::PartialOrd for Operator {
-    fn partial_cmp(&self, other: &Operator) -> Option<cmp::Ordering> {
-        Some(self.get_precedence().cmp(&other.get_precedence()))
+
+    fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Operator::Substract => &["−"],
+            Operator::Multiply => &["×", "·", "⋅"],
+            Operator::Divide => &["÷"],
+            Operator::IsGreaterThanOrEqualTo => &["≥"],
+            Operator::IsLessThanOrEqualTo => &["≤"],
+            Operator::IsNotEqualTo => &["≠"],
+            Operator::Not => &["¬"],
+            Operator::And => &["and", "∧"],
+            Operator::Or => &["or", "∨"],
+            Operator::Xor => &["⊻"],
+            Operator::Conditional => &["?"],
+            _ => &[],
+        }
     }
-}
 
-impl Operator {
     pub fn get_precedence(&self) -> i8 {
         match self {
-            Operator::Add => 2,
-            Operator::Substract => 2,
-            Operator::Multiply => 3,
-            Operator::Divide => 3,
-            Operator::Exponentiate => 4,
+            Operator::Conditional => 0,
+            Operator::Or => 10,
+            Operator::Xor => 20,
+            Operator::And => 30,
             Operator::IsGreaterThan
             | Operator::IsLessThan
             | Operator::IsGreaterThanOrEqualTo
             | Operator::IsLessThanOrEqualTo
             | Operator::IsEqualTo
-            | Operator::IsNotEqualTo
-            | Operator::Not => 2,
+            | Operator::IsNotEqualTo => 40,
+            Operator::Add | Operator::Substract | Operator::Not => 50,
+            Operator::Multiply
+            | Operator::Divide
+            | Operator::Remainder
+            | Operator::Modulo
+            | Operator::FloorDivide => 60,
+            Operator::Exponentiate => 70,
+            Operator::Factorial
+            | Operator::DoubleFactorial
+            | Operator::Percent => 80,
         }
     }
 
     pub fn num_operands(&self) -> i8 {
         match self {
-            Operator::Not => 1,
+            Operator::Not
+            | Operator::Factorial
+            | Operator::DoubleFactorial
+            | Operator::Percent => 1,
+            Operator::Conditional => 3,
             _ => 2,
         }
     }
 
+    /// Whether the operator comes before its only operand, as in `!x`.
+    pub fn is_prefix(&self) -> bool {
+        matches!(self, Operator::Not)
+    }
+
+    /// Whether the operator follows its operand, as in `5!`.
+    pub fn is_postfix(&self) -> bool {
+        matches!(
+            self,
+            Operator::Factorial | Operator::DoubleFactorial | Operator::Percent
+        )
+    }
+
     pub fn is_right_associative(&self) -> bool {
-        *self == Operator::Exponentiate
+        matches!(
+            self,
+            Operator::Exponentiate | Operator::Conditional | Operator::Not
+        )
     }
 }
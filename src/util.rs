@@ -1,14 +1,14 @@
 use crate::error::Error;
-use std::{iter::Peekable, str};
+use std::{f64, iter::Peekable, str};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-pub fn consume_while<F>(it: &mut Peekable<str::Chars>, x: F) -> String
+pub fn consume_while<F>(it: &mut Peekable<str::CharIndices>, x: F) -> String
 where
     F: Fn(char) -> bool,
 {
     let mut s = String::with_capacity(5);
-    while let Some(&ch) = it.peek() {
+    while let Some(&(_, ch)) = it.peek() {
         if x(ch) {
             it.next().unwrap();
             s.push(ch);
@@ -18,3 +18,99 @@ where
     }
     s
 }
+
+/// Levenshtein distance between two strings, counted in chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            }
+            else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+const LANCZOS_G: f64 = 7.0;
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+// Past this, gamma overflows an f64
+const MAX_GAMMA: f64 = 171.7;
+
+/// The gamma function, using the Lanczos approximation.
+pub fn gamma(x: f64) -> f64 {
+    if x > MAX_GAMMA {
+        return f64::INFINITY;
+    }
+    if x < 0.5 {
+        // Reflection formula
+        return f64::consts::PI
+            / ((f64::consts::PI * x).sin() * gamma(1.0 - x));
+    }
+
+    let x = x - 1.0;
+    let t = x + LANCZOS_G + 0.5;
+    let sum = LANCZOS[1..]
+        .iter()
+        .enumerate()
+        .fold(LANCZOS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+
+    // Halving the power keeps it finite until the result itself overflows
+    let power = t.powf((x + 0.5) / 2.0);
+    (2.0 * f64::consts::PI).sqrt() * power * (-t).exp() * power * sum
+}
+
+fn is_whole(n: f64) -> bool {
+    n.fract() == 0.0
+}
+
+/// `n!`, extended to non-integers as `gamma(n + 1)`. Negative integers have
+/// no factorial and give NaN.
+pub fn factorial(n: f64) -> f64 {
+    if is_whole(n) && n < 0.0 {
+        f64::NAN
+    }
+    else if is_whole(n) && n <= 170.0 {
+        (2..=n as u64).map(|k| k as f64).product()
+    }
+    else {
+        gamma(n + 1.0)
+    }
+}
+
+/// `n!!`, the product of every other integer down from `n`. Only defined for
+/// integers from `-1` up; anything else gives NaN.
+pub fn double_factorial(n: f64) -> f64 {
+    if !is_whole(n) || n < -1.0 {
+        return f64::NAN;
+    }
+    let mut product: f64 = 1.0;
+    let mut k = n;
+    // Past a few hundred the product overflows, and above 2^53 `k - 2.0`
+    // no longer changes `k`
+    while k > 1.0 && product.is_finite() {
+        product *= k;
+        k -= 2.0;
+    }
+    product
+}
@@ -1,4 +1,10 @@
-use asciimath::{eval, CustomFn, Error, Scope};
+use asciimath::{
+    compile, compile_declared, compile_recovering, eval, scope, walk_expr_mut,
+    Associativity, BinaryOp, CompiledExpr, CustomFn, CustomOperator,
+    Declarations, Diagnostic, Error, Evaluate, Expr, Fixity,
+    ImplicitMultiplication, Parser, ParserOptions, PrintOptions, Scope, Span,
+    SeriesOp, Syntax, UnaryOp, Visitor, VisitorMut,
+};
 use std::f64;
 
 #[test]
@@ -40,7 +46,7 @@ fn simple_vars() {
     assert_eq!(Ok(240.0), eval("x^2-16", &scope));
 
     assert_eq!(
-        Err(Error::UnknownVariable("y".to_string())),
+        Err(Error::UnknownVariable("y".to_string(), Span::new(0, 1))),
         eval("y^2-16", &scope)
     );
 }
@@ -89,6 +95,7 @@ fn func_trig() {
 fn func_log() {
     assert_eq!(Ok(2.0), eval("log_10(100)", &Scope::new()));
     assert_eq!(Ok(2.0), eval("log(2, 4)", &Scope::new()));
+    assert_eq!(Ok(3.0), eval("log(1000)", &Scope::new()));
     assert_eq!(Ok(1.0), eval("ln(E)", &Scope::new()));
 }
 
@@ -125,7 +132,10 @@ fn neg_fn() {
 #[test]
 fn func_not_enough_args() {
     assert_eq!(
-        Err(Error::NotEnoughFunctionParams("max".to_string())),
+        Err(Error::NotEnoughFunctionParams(
+            "max".to_string(),
+            Span::new(0, 5)
+        )),
         eval("max()", &Scope::new())
     );
 }
@@ -140,7 +150,1792 @@ fn constants() {
     assert_eq!(Ok(f64::consts::PI * 2.0), eval("2PI", &Scope::new()));
 }
 
+#[test]
+fn numeric_literals() {
+    let scope = scope! { "x" => 2 };
+
+    assert_eq!(Ok(0.003), eval("1.5e-3 * x", &scope));
+    assert_eq!(Ok(32.0), eval("0x1F + 0b1", &scope));
+    assert_eq!(Ok(2_000_000.0), eval("1_000_000x", &scope));
+}
+
+#[test]
+fn unicode_symbols() {
+    let scope = scope! { "r" => 2 };
+
+    assert_eq!(Ok(4.0 * f64::consts::PI), eval("π·r²", &scope));
+    assert_eq!(Ok(-3.0), eval("−6 ÷ 2", &scope));
+    assert_eq!(Ok(1.0), eval("2 × 3 ≥ 6", &scope));
+    assert_eq!(Ok(5.0), eval("√16 + ∛1", &scope));
+    assert_eq!(Ok(4.0), eval("√(3² + 7)", &scope));
+    assert_eq!(Ok(0.5), eval("r⁻¹", &scope));
+    assert_eq!(Ok(0.25), eval("r⁻²", &scope));
+    assert_eq!(Ok(-0.5), eval("-r⁻¹", &scope));
+    assert_eq!(Ok(f64::INFINITY), eval("∞", &scope));
+}
+
 #[test]
 fn division_by_zero() {
     assert_eq!(Ok(f64::INFINITY), eval("1/0", &Scope::new()));
 }
+
+#[test]
+fn error_spans() {
+    let scope = Scope::new();
+
+    assert_eq!(Span::new(4, 5), eval("2 + y", &scope).unwrap_err().span());
+    assert_eq!(
+        Err(Error::MissingOperands("*".to_string(), Span::new(2, 3))),
+        eval("2 *", &scope)
+    );
+    assert_eq!(
+        Err(Error::EmptyExpression(Span::new(0, 2))),
+        eval("  ", &scope)
+    );
+}
+
+#[test]
+fn node_spans() {
+    let scope = Scope::new();
+    let root = compile("1 + max(2, 3)", &scope).unwrap();
+
+    assert_eq!(Span::new(0, 13), root.node().span);
+    let args = root.node().args.as_ref().unwrap();
+    assert_eq!(Span::new(0, 1), args[0].span);
+    assert_eq!(Span::new(4, 13), args[1].span);
+
+    // Errors found while evaluating span the whole node they come from
+    let names = Declarations::new().function("f");
+    let expr = compile_declared("1 + f(2, 3)", &names).unwrap();
+    assert_eq!(
+        Err(Error::UnknownFunction("f".to_string(), Span::new(4, 11))),
+        expr.eval()
+    );
+    assert_eq!(
+        Err(Error::DivisionByZero(Span::new(4, 9))),
+        eval("1 + 2 % 0 * 3", &Scope::new())
+    );
+}
+
+#[test]
+fn diagnostic_underline() {
+    let expr = "2 + y * 3";
+    let err = eval(expr, &Scope::new()).unwrap_err();
+
+    assert_eq!(
+        Diagnostic::new(&err, expr)
+            .with_note("variables are case-sensitive")
+            .to_string(),
+        format!(
+            "error: {}\n  |\n1 | 2 + y * 3\n  |     ^\n  = variables are \
+             case-sensitive",
+            err
+        )
+    );
+
+    // A span from another expression is kept within this one
+    let err = Error::InvalidToken("x".to_string(), Span::new(1, 9));
+    assert_eq!(
+        Diagnostic::new(&err, "é").to_string(),
+        "error: Invalid token: \"x\"\n  |\n1 | é\n  | ^"
+    );
+}
+
+#[test]
+fn diagnostic_suggestions() {
+    let scope = scope! { "width" => 2, "height" => 3 };
+
+    let expr = "2 * sqr(16)";
+    let err = eval(expr, &scope).unwrap_err();
+    let diagnostic = Diagnostic::new(&err, expr).with_suggestions(&scope);
+    assert_eq!(diagnostic.notes(), ["did you mean `sqrt`?"]);
+    assert!(diagnostic.to_string().contains("1 | 2 * sqr(16)\n  |     ^^^\n"));
+
+    let expr = "widht * height";
+    let err = eval(expr, &scope).unwrap_err();
+    let diagnostic = Diagnostic::new(&err, expr).with_suggestions(&scope);
+    assert_eq!(diagnostic.notes(), ["did you mean `width`?"]);
+    assert_eq!(
+        Diagnostic::new(&err, expr).to_string(),
+        "error: Variable \"widht\" is not defined. Make sure you are passing \
+         it through the Scope.\n  |\n1 | widht * height\n  | ^^^^^"
+    );
+
+    let expr = "1 +";
+    let err = eval(expr, &scope).unwrap_err();
+    assert!(Diagnostic::new(&err, expr).suggestions(&scope).is_empty());
+}
+
+#[test]
+fn unbalanced_parentheses() {
+    let scope = Scope::new();
+
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(2, 3))),
+        eval("2*(1+2", &scope)
+    );
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(3, 4))),
+        eval("1+2)", &scope)
+    );
+}
+
+#[test]
+fn func_bad_args() {
+    let scope = Scope::new();
+
+    assert_eq!(Ok(3.0), eval("max(1+2, 3)", &scope));
+    assert_eq!(
+        Err(Error::FunctionSyntaxError(
+            "max".to_string(),
+            Span::new(5, 6)
+        )),
+        eval("max(1,)", &scope)
+    );
+    assert_eq!(
+        Err(Error::FunctionSyntaxError(
+            "max".to_string(),
+            Span::new(4, 5)
+        )),
+        eval("max(,1)", &scope)
+    );
+    assert_eq!(
+        Err(Error::MissingOperands("*".to_string(), Span::new(8, 9))),
+        eval("2 + max(* 3)", &scope)
+    );
+}
+
+#[test]
+fn recover_all_errors() {
+    let scope = Scope::new();
+    let (root, errors) = compile_recovering("max(1,,2) + (3 * ) + (4", &scope);
+
+    assert_eq!(
+        errors,
+        vec![
+            Error::FunctionSyntaxError("max".to_string(), Span::new(6, 7)),
+            Error::MissingOperands("*".to_string(), Span::new(15, 16)),
+            Error::UnbalancedParentheses(Span::new(21, 22)),
+        ]
+    );
+    assert_eq!(Err(errors[0].clone()), root.eval());
+
+    for expr in &["1 2 3", "(1 + 2", "1 + 2)", "1 ? 2 : ) + 3", "max(1 2"] {
+        let (root, errors) = compile_recovering(expr, &scope);
+        assert!(!errors.is_empty(), "{}", expr);
+        assert_eq!(Err(errors[0].clone()), root.eval(), "{}", expr);
+    }
+
+    // Placeholders in branches that are never evaluated still fail
+    for expr in &["1 ? 2", "x or y and", "if(1, 2, )"] {
+        let scope = scope! { "x" => 1, "y" => 0 };
+        let (root, errors) = compile_recovering(expr, &scope);
+        assert!(!errors.is_empty(), "{}", expr);
+        assert_eq!(Err(errors[0].clone()), root.eval(), "{}", expr);
+    }
+
+    let (root, errors) = compile_recovering("1 + 2", &scope);
+    assert!(errors.is_empty());
+    assert_eq!(Ok(3.0), root.eval());
+
+    let (_, errors) = compile_recovering("", &scope);
+    assert_eq!(errors, vec![Error::EmptyExpression(Span::new(0, 0))]);
+}
+
+#[test]
+fn strict_unknown_characters() {
+    let scope = Scope::new();
+
+    for (expr, ch, at) in
+        &[("2 $ 3", "$", 2), ("#1", "#", 0), ("4 @ 2", "@", 2)]
+    {
+        assert_eq!(
+            Err(Error::InvalidToken(ch.to_string(), Span::new(*at, at + 1))),
+            eval(expr, &scope)
+        );
+    }
+
+    let (_, errors) = compile_recovering("1 $ 2 # 3", &scope);
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn lenient_unknown_characters() {
+    let parser = Parser::new(ParserOptions {
+        strict: false,
+        ..ParserOptions::default()
+    });
+
+    assert_eq!(Ok(6.0), parser.eval("2 * 3 #", &Scope::new()));
+    assert_eq!(Ok(5.0), parser.eval("$2 + 3", &Scope::new()));
+}
+
+#[test]
+fn division_operators() {
+    let scope = Scope::new();
+
+    assert_eq!(Ok(1.0), eval("7 % 3", &scope));
+    assert_eq!(Ok(-1.0), eval("-7 % 3", &scope));
+    assert_eq!(Ok(1.5), eval("5.5 % 2", &scope));
+    assert_eq!(Ok(2.0), eval("-7 mod 3", &scope));
+    assert_eq!(Ok(-2.0), eval("7 mod (-3)", &scope));
+    assert_eq!(Ok(3.0), eval("7 // 2", &scope));
+    assert_eq!(Ok(-4.0), eval("-7 // 2", &scope));
+    assert_eq!(Ok(2.0), eval("2 * 7 mod 4", &scope));
+    assert_eq!(Ok(7.0), eval("1 + 12 // 2", &scope));
+    assert_eq!(Ok(1.0), eval("x mod 3", &scope! { "x" => 10 }));
+}
+
+#[test]
+fn remainder_by_zero() {
+    let scope = Scope::new();
+
+    assert_eq!(
+        Err(Error::DivisionByZero(Span::new(0, 5))),
+        eval("7 % 0", &scope)
+    );
+    assert_eq!(
+        Err(Error::DivisionByZero(Span::new(0, 7))),
+        eval("7 mod 0", &scope)
+    );
+    assert_eq!(
+        Err(Error::DivisionByZero(Span::new(0, 4))),
+        eval("7//0", &scope)
+    );
+}
+
+#[test]
+fn logical_operators() {
+    let scope = scope! { "x" => 3 };
+
+    assert_eq!(Ok(1.0), eval("x > 0 && x < 5", &scope));
+    assert_eq!(Ok(0.0), eval("x > 0 and x > 5", &scope));
+    assert_eq!(Ok(1.0), eval("x < 0 || x == 3", &scope));
+    assert_eq!(Ok(0.0), eval("0 or 0", &scope));
+    assert_eq!(Ok(1.0), eval("1 xor 0", &scope));
+    assert_eq!(Ok(0.0), eval("2 xor 5", &scope));
+    assert_eq!(Ok(1.0), eval("1 || 0 && 0", &scope));
+    assert_eq!(Ok(1.0), eval("!0 && 1", &scope));
+    assert_eq!(Ok(0.0), eval("2 > 1 + 1", &scope));
+    assert_eq!(
+        Err(Error::InvalidToken("&".to_string(), Span::new(2, 3))),
+        eval("1 & 2", &scope)
+    );
+}
+
+#[test]
+fn logical_short_circuit() {
+    let mut scope = Scope::new();
+    let fail: CustomFn = |_| panic!("right operand evaluated");
+    scope.set_var("fail", fail);
+
+    assert_eq!(Ok(0.0), eval("0 && fail(1)", &scope));
+    assert_eq!(Ok(1.0), eval("2 || fail(1)", &scope));
+    assert_eq!(Ok(1.0), eval("1 or 1 % 0", &scope));
+}
+
+#[test]
+fn conditionals() {
+    let scope = scope! { "x" => 4 };
+
+    assert_eq!(Ok(2.0), eval("1 ? 2 : 3", &scope));
+    assert_eq!(Ok(-4.0), eval("x < 0 ? x : -x", &scope));
+    assert_eq!(Ok(3.0), eval("0 ? 1 : 0 ? 2 : 3", &scope));
+    assert_eq!(Ok(6.0), eval("1 ? 0 ? 5 : 6 : 7", &scope));
+    assert_eq!(Ok(4.0), eval("1 + 1 ? 2 + 2 : 3", &scope));
+    assert_eq!(Ok(2.0), eval("max(x > 2 ? 2 : 3, 1)", &scope));
+    assert_eq!(Ok(1.0), eval("if(x > 3, 1, 2)", &scope));
+    assert_eq!(
+        Err(Error::IncompleteConditional(Span::new(2, 3))),
+        eval("1 ? 2", &scope)
+    );
+    assert_eq!(
+        Err(Error::InvalidToken(":".to_string(), Span::new(2, 3))),
+        eval("1 : 2", &scope)
+    );
+    assert_eq!(
+        Err(Error::NotEnoughFunctionParams(
+            "if".to_string(),
+            Span::new(0, 7)
+        )),
+        eval("if(1,2)", &scope)
+    );
+}
+
+#[test]
+fn conditional_branches_are_lazy() {
+    let mut scope = Scope::new();
+    let fail: CustomFn = |_| panic!("untaken branch evaluated");
+    scope.set_var("fail", fail);
+
+    assert_eq!(Ok(1.0), eval("1 ? 1 : fail(0)", &scope));
+    assert_eq!(Ok(2.0), eval("0 ? fail(0) : 2", &scope));
+    assert_eq!(Ok(1.0), eval("if(1, 1, fail(0))", &scope));
+    assert_eq!(Ok(2.0), eval("if(0, 1 % 0, 2)", &scope));
+}
+
+#[test]
+fn factorials() {
+    let scope = scope! { "x" => 4 };
+
+    assert_eq!(Ok(120.0), eval("5!", &scope));
+    assert_eq!(Ok(1.0), eval("0!", &scope));
+    assert_eq!(Ok(24.0), eval("x!", &scope));
+    assert_eq!(Ok(3.0), eval("(1 + 2)!!", &scope));
+    assert_eq!(Ok(48.0), eval("6!!", &scope));
+    assert_eq!(Ok(f64::INFINITY), eval("400!!", &scope));
+    assert_eq!(Ok(f64::INFINITY), eval("1e18!!", &scope));
+    assert_eq!(Ok(f64::INFINITY), eval("1000!", &scope));
+    assert_eq!(Ok(f64::INFINITY), eval("10!!!", &scope));
+    assert_eq!(Ok(f64::INFINITY), eval("171.5!", &scope));
+    assert_eq!(
+        Ok(f64::INFINITY),
+        eval("x!", &scope! { "x" => f32::INFINITY })
+    );
+    assert!((eval("170.5!", &scope).unwrap() / 9.48e307 - 1.0).abs() < 0.01);
+    assert_eq!(Ok(64.0), eval("2^3!", &scope));
+    assert_eq!(Ok(-6.0), eval("-3!", &scope));
+    assert!(
+        (eval("0.5!", &scope).unwrap() - f64::consts::PI.sqrt() / 2.0).abs()
+            < 1e-12
+    );
+    assert!(eval("(-1)!", &scope).unwrap().is_nan());
+
+    // `!` is still a prefix `Not` where no operand precedes it
+    assert_eq!(Ok(1.0), eval("x != 3", &scope));
+    assert_eq!(Ok(1.0), eval("3!=5", &scope));
+    assert_eq!(Ok(1.0), eval("!!2", &scope));
+    assert_eq!(Ok(0.0), eval("1 - !0", &scope));
+}
+
+#[test]
+fn percentages() {
+    let scope = scope! { "x" => 50 };
+
+    assert_eq!(Ok(0.2), eval("20%", &scope));
+    assert_eq!(Ok(30.0), eval("200 * 15%", &scope));
+    assert_eq!(Ok(1.0), eval("x% * 2", &scope));
+    assert_eq!(Ok(0.1), eval("(5 + 5)%", &scope));
+    assert_eq!(Ok(2.0), eval("x % 3", &scope));
+    // A minus before an operand negates it
+    assert_eq!(Ok(1.0), eval("10 % -3", &scope));
+    assert_eq!(Ok(1.0), eval("10 % - 3", &scope));
+    assert_eq!(Ok(-0.5), eval("(x%) - 1", &scope));
+}
+
+#[test]
+fn absolute_value_bars() {
+    let scope = scope! { "x" => -4 };
+
+    assert_eq!(Ok(3.0), eval("|-3|", &scope));
+    assert_eq!(Ok(4.0), eval("||-1| - |5||", &scope));
+    assert_eq!(Ok(8.0), eval("2|x|", &scope));
+    assert_eq!(Ok(8.0), eval("|x|2", &scope));
+    assert_eq!(Ok(16.0), eval("|x|^2", &scope));
+    assert_eq!(Ok(-1.0), eval("|-2|-|-3|", &scope));
+    assert_eq!(Ok(2.0), eval("√|x|", &scope));
+    assert_eq!(Ok(4.0), eval("max(|x|, 1)", &scope));
+    assert_eq!(Ok(1.0), eval("|1| || |0|", &scope));
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(0, 1))),
+        eval("|x", &scope)
+    );
+    // A bar with nothing to close, like a stray `)`
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(1, 2))),
+        eval("x|", &scope)
+    );
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(0, 1))),
+        eval("|", &scope)
+    );
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(4, 5))),
+        eval("|x|x|", &scope)
+    );
+}
+
+#[test]
+fn malformed_input() {
+    let scope = Scope::new();
+
+    assert_eq!(
+        Err(Error::UnexpectedToken("2".to_string(), Span::new(2, 3))),
+        eval("1 2 +", &scope)
+    );
+    assert_eq!(
+        Err(Error::UnexpectedToken(",".to_string(), Span::new(2, 3))),
+        eval("(1, 2)", &scope)
+    );
+    assert_eq!(
+        Err(Error::MissingOperands("+".to_string(), Span::new(0, 1))),
+        eval("+ 1", &scope)
+    );
+    assert_eq!(
+        Err(Error::MissingOperands("^".to_string(), Span::new(2, 3))),
+        eval("2 ^ * 3", &scope)
+    );
+    assert_eq!(
+        Err(Error::FunctionSyntaxError(
+            "max".to_string(),
+            Span::new(6, 7)
+        )),
+        eval("max(1 2)", &scope)
+    );
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(0, 1))),
+        eval(")1", &scope)
+    );
+    assert_eq!(
+        Err(Error::EmptyExpression(Span::new(0, 2))),
+        eval("()", &scope)
+    );
+
+    let (_, errors) = compile_recovering("1 2 + (3 4) +", &scope);
+    assert_eq!(
+        errors,
+        vec![
+            Error::UnexpectedToken("2".to_string(), Span::new(2, 3)),
+            Error::UnexpectedToken("4".to_string(), Span::new(9, 10)),
+            Error::MissingOperands("+".to_string(), Span::new(12, 13)),
+        ]
+    );
+}
+
+#[test]
+fn unary_minus() {
+    let scope = scope! { "x" => 3 };
+
+    assert_eq!(Ok(-4.0), eval("8/-2", &scope));
+    assert_eq!(Ok(-9.0), eval("-x^2", &scope));
+    assert_eq!(Ok(0.5), eval("2^-1", &scope));
+    assert_eq!(Ok(-5.0), eval("2*-3+1", &scope));
+    assert_eq!(Ok(2.0), eval("1 - -1", &scope));
+    assert_eq!(
+        Err(Error::MissingOperands("-".to_string(), Span::new(0, 1))),
+        eval("-", &scope)
+    );
+}
+
+#[test]
+fn custom_operators() {
+    let shl: CustomFn = |args| Ok(args[0] * 2f64.powf(args[1]));
+    let bit_and: CustomFn = |args| Ok((args[0] as i64 & args[1] as i64) as f64);
+    let bit_or: CustomFn = |args| Ok((args[0] as i64 | args[1] as i64) as f64);
+    let nand: CustomFn =
+        |args| Ok(!(args[0] != 0.0 && args[1] != 0.0) as u8 as f64);
+    let degrees: CustomFn = |args| Ok(args[0].to_radians());
+    let pow: CustomFn = |args| Ok(args[0].powf(args[1]));
+
+    let parser = Parser::builder()
+        .infix("<<", 45, Associativity::Left, shl)
+        .infix("&", 35, Associativity::Left, bit_and)
+        .infix("|", 32, Associativity::Left, bit_or)
+        .infix("nand", 30, Associativity::Left, nand)
+        .postfix("°", 80, degrees)
+        .infix("^", 70, Associativity::Left, pow)
+        .build()
+        .unwrap();
+    let scope = scope! { "x" => 6 };
+
+    assert_eq!(Ok(12.0), parser.eval("1 + 2 << 2", &scope));
+    assert_eq!(Ok(1.0), parser.eval("1 << 1 > 1", &scope));
+    assert_eq!(Ok(2.0), parser.eval("x & 3", &scope));
+    assert_eq!(Ok(7.0), parser.eval("x | 1", &scope));
+    assert_eq!(Ok(1.0), parser.eval("x && 1", &scope));
+    assert_eq!(Ok(0.0), parser.eval("1 nand x", &scope));
+    assert_eq!(Ok(f64::consts::PI), parser.eval("180°", &scope));
+    assert_eq!(Ok(f64::consts::PI - 1.0), parser.eval("180° - 1", &scope));
+    assert_eq!(Ok(f64::consts::PI / 100.0), parser.eval("180°%", &scope));
+    assert_eq!(Ok(1.0), parser.eval("x% nand 0", &scope));
+    // `^` is now left-associative
+    assert_eq!(Ok(64.0), parser.eval("2^3^2", &scope));
+}
+
+#[test]
+fn custom_prefix_and_infix() {
+    let plus_minus: CustomFn = |args| Ok(args[0] + args[1].abs());
+    let abs: CustomFn = |args| Ok(args[0].abs());
+
+    let parser = Parser::builder()
+        .infix("±", 50, Associativity::Left, plus_minus)
+        .prefix("±", 50, abs)
+        .build()
+        .unwrap();
+    let scope = Scope::new();
+
+    assert_eq!(Ok(5.0), parser.eval("2 ± -3", &scope));
+    assert_eq!(Ok(3.0), parser.eval("±-3", &scope));
+    assert_eq!(
+        Err(Error::MissingOperands("±".to_string(), Span::new(2, 4))),
+        parser.eval("2 ±", &scope)
+    );
+}
+
+#[test]
+fn custom_operator_precedence_bounds() {
+    let first: CustomFn = |args| Ok(args[0]);
+    let negate: CustomFn = |args| Ok(-args[0]);
+    let parser = Parser::builder()
+        .infix("@", i8::MAX - 1, Associativity::Left, first)
+        .prefix("~", i8::MAX - 1, negate)
+        .build()
+        .unwrap();
+    let scope = Scope::new();
+
+    assert_eq!(Ok(1.0), parser.eval("1 @ 2 @ 3", &scope));
+    assert_eq!(Ok(-2.0), parser.eval("~2", &scope));
+    // Tighter than `^`
+    assert_eq!(Ok(12.0), parser.eval("~2^2 + 8", &scope));
+}
+
+#[test]
+fn invalid_custom_operators() {
+    let first: CustomFn = |args| Ok(args[0]);
+    let left = Associativity::Left;
+
+    let built = Parser::builder().infix("@", i8::MAX, left, first).build();
+    assert_eq!(
+        Some(Error::OperatorPrecedence("@".to_string())),
+        built.err()
+    );
+    let built = Parser::builder().postfix("~", i8::MAX, first).build();
+    assert_eq!(
+        Some(Error::OperatorPrecedence("~".to_string())),
+        built.err()
+    );
+
+    for symbol in &["", "a1", "<a", "(", "x y"] {
+        let built = Parser::builder().prefix(symbol, 50, first).build();
+        assert_eq!(
+            Some(Error::InvalidOperatorSymbol(symbol.to_string())),
+            built.err()
+        );
+    }
+
+    let built = Parser::builder()
+        .infix("~", 50, left, first)
+        .postfix("~", 80, first)
+        .build();
+    assert_eq!(Some(Error::AmbiguousOperator("~".to_string())), built.err());
+    let built = Parser::builder()
+        .postfix("~", 80, first)
+        .infix("~", 50, left, first)
+        .build();
+    assert_eq!(Some(Error::AmbiguousOperator("~".to_string())), built.err());
+
+    // A later definition of the same kind replaces the earlier one
+    let parser = Parser::builder()
+        .infix("~", 50, left, first)
+        .infix("~", 60, left, |args| Ok(args[1]))
+        .build()
+        .unwrap();
+    assert_eq!(Ok(2.0), parser.eval("1 ~ 2", &Scope::new()));
+
+    // There is nothing in the expression to underline
+    let err = Error::AmbiguousOperator("~".to_string());
+    assert_eq!(
+        Diagnostic::new(&err, "1 ~ 2").to_string(),
+        "error: Operator \"~\" cannot be both infix and postfix"
+    );
+}
+
+#[test]
+fn disabled_operators() {
+    let parser = Parser::builder().disable("^").disable("&&").build().unwrap();
+    let scope = scope! { "x" => 2 };
+
+    assert_eq!(
+        Err(Error::InvalidToken("^".to_string(), Span::new(1, 2))),
+        parser.eval("x^2", &scope)
+    );
+    assert_eq!(
+        Err(Error::InvalidToken("²".to_string(), Span::new(1, 3))),
+        parser.eval("x²", &scope)
+    );
+    assert_eq!(
+        Err(Error::InvalidToken("and".to_string(), Span::new(2, 5))),
+        parser.eval("1 and 1", &scope)
+    );
+    assert_eq!(Ok(4.0), parser.eval("x * 2", &scope));
+    assert_eq!(Ok(4.0), eval("x^2", &scope));
+}
+
+#[test]
+fn custom_operators_need_plain_syntax() {
+    let shl: CustomFn = |args| Ok(args[0] * 2f64.powf(args[1]));
+    let built = Parser::builder()
+        .options(ParserOptions {
+            syntax: Syntax::AsciiMath,
+            ..ParserOptions::default()
+        })
+        .infix("<<", 45, Associativity::Left, shl)
+        .build();
+    let err = Error::CustomOperatorSyntax("<<".to_string());
+    assert_eq!(Some(err.clone()), built.err());
+    let diagnostic = Diagnostic::new(&err, "1 << 2");
+    assert_eq!(
+        diagnostic.with_note("remove the operator").to_string(),
+        "error: Custom operator \"<<\" can only be read with Syntax::Plain\n  \
+         = remove the operator"
+    );
+}
+
+fn implicit_parser(
+    implicit_multiplication: ImplicitMultiplication,
+    implicit_binds_tighter: bool,
+) -> Parser {
+    Parser::new(ParserOptions {
+        implicit_multiplication,
+        implicit_binds_tighter,
+        ..ParserOptions::default()
+    })
+}
+
+#[test]
+fn implicit_multiplication_modes() {
+    let scope = scope! { "x" => 2, "y" => 3 };
+
+    let full = implicit_parser(ImplicitMultiplication::Full, false);
+    assert_eq!(Ok(6.0), full.eval("xy", &scope));
+    assert_eq!(Ok(12.0), full.eval("(x)(2y)", &scope));
+
+    let numbers =
+        implicit_parser(ImplicitMultiplication::NumbersAndParens, false);
+    assert_eq!(Ok(6.0), numbers.eval("2y", &scope));
+    assert_eq!(Ok(6.0), numbers.eval("(x)(y)", &scope));
+    assert_eq!(Ok(10.0), numbers.eval("2(x + y)", &scope));
+    assert_eq!(
+        Err(Error::UnknownVariable("xy".to_string(), Span::new(0, 2))),
+        numbers.eval("xy", &scope)
+    );
+
+    let disabled = implicit_parser(ImplicitMultiplication::Disabled, false);
+    assert_eq!(Ok(6.0), disabled.eval("x * y", &scope));
+    assert_eq!(
+        Err(Error::ImplicitMultiplication(Span::new(1, 1))),
+        disabled.eval("2y", &scope)
+    );
+    assert_eq!(
+        Err(Error::ImplicitMultiplication(Span::new(3, 3))),
+        disabled.eval("(x)(y)", &scope)
+    );
+}
+
+#[test]
+fn implicit_multiplication_precedence() {
+    let scope = scope! { "x" => 2 };
+
+    let looser = implicit_parser(ImplicitMultiplication::Full, false);
+    assert_eq!(Ok(1.0), looser.eval("1/2x", &scope));
+
+    let tighter = implicit_parser(ImplicitMultiplication::Full, true);
+    assert_eq!(Ok(0.25), tighter.eval("1/2x", &scope));
+    assert_eq!(Ok(1.0), tighter.eval("1/2 * x", &scope));
+    assert_eq!(Ok(8.0), tighter.eval("2x^2", &scope));
+}
+
+#[test]
+fn function_application() {
+    let parser = Parser::new(ParserOptions {
+        function_application: true,
+        ..ParserOptions::default()
+    });
+    let scope = scope! { "x" => 3 };
+    let eval = |expr| parser.eval(expr, &scope);
+
+    assert_eq!(Ok(2.0), eval("sqrt 4"));
+    assert_eq!(eval("sin(x^2)"), eval("sin x^2"));
+    assert_eq!(eval("ln(2x)"), eval("ln 2x"));
+    assert_eq!(eval("cos(π)"), eval("cos π"));
+    assert_eq!(eval("cos(PI)"), eval("cos PI"));
+    assert_eq!(eval("sin(x) * 2"), eval("sin x * 2"));
+    assert_eq!(eval("sin(x) + 1"), eval("sin x + 1"));
+    assert_eq!(eval("sin(-x^2)"), eval("sin -x^2"));
+    assert_eq!(eval("sin(cos(x))"), eval("sin cos x"));
+    // A function after an operand multiplies it, and ends the argument of
+    // the function before it
+    assert_eq!(eval("2 * sin(x)"), eval("2 sin x"));
+    assert_eq!(eval("sin(x) * sin(2x)"), eval("sin x sin 2x"));
+    assert_eq!(eval("x * sin(x)"), eval("x sin(x)"));
+    assert_eq!(
+        "sin(x) * cos(x)",
+        parser.compile("sin x cos x", &scope).unwrap().to_string()
+    );
+    let numbers = Parser::new(ParserOptions {
+        function_application: true,
+        implicit_multiplication: ImplicitMultiplication::NumbersAndParens,
+        ..ParserOptions::default()
+    });
+    assert_eq!(eval("2 * sin(x)"), numbers.eval("2 sin x", &scope));
+    assert_eq!(
+        Err(Error::ImplicitMultiplication(Span::new(5, 5))),
+        numbers.eval("sin x sin x", &scope)
+    );
+    assert_eq!(Ok(6f64.sqrt()), eval("sqrt x!"));
+    assert_eq!(Ok(5.0), eval("max(x, 5)"));
+    assert_eq!(Ok(2.0), eval("sqrt (1 + 3)"));
+    assert_eq!(Ok(2.0), eval("log 100"));
+    assert_eq!(Ok(3.0), eval("log(2, 8)"));
+    assert_eq!(eval("log(100)"), eval("log 100"));
+    assert_eq!(
+        Err(Error::NotEnoughFunctionParams(
+            "sqrt".to_string(),
+            Span::new(0, 4)
+        )),
+        eval("sqrt + 1")
+    );
+
+    // Without the option, a function needs its parentheses
+    assert_eq!(
+        Err(Error::UnexpectedToken("x".to_string(), Span::new(4, 5))),
+        Parser::default().eval("sin x", &scope)
+    );
+}
+
+#[test]
+fn identifiers() {
+    let scope = scope! {
+        "x1" => 2,
+        "v_0" => 3,
+        "alpha_max" => 4,
+        "temp2" => 5,
+        "x_ij" => 6,
+    };
+
+    assert_eq!(Ok(10.0), eval("x1 * temp2", &scope));
+    assert_eq!(Ok(12.0), eval("v_0 * alpha_max", &scope));
+    assert_eq!(Ok(6.0), eval("x_{ij}", &scope));
+    assert_eq!(Ok(6.0), eval("x_ij", &scope));
+    assert_eq!(Ok(9.0), eval("3v_0", &scope));
+    assert_eq!(
+        Err(Error::UnknownVariable("x2".to_string(), Span::new(0, 2))),
+        eval("x2", &scope)
+    );
+}
+
+#[test]
+fn greek_letters() {
+    let scope = scope! {
+        "alpha" => 2,
+        "θ" => 90,
+        "lambda_0" => 3,
+    };
+
+    assert_eq!(Ok(2.0), eval("α", &scope));
+    assert_eq!(Ok(2.0), eval("alpha", &scope));
+    assert_eq!(Ok(90.0), eval("theta", &scope));
+    assert_eq!(Ok(1.0), eval("sin(θ)", &scope));
+    assert_eq!(Ok(3.0), eval("λ_0", &scope));
+    assert_eq!(Ok(4.0), eval("2α", &scope));
+    assert_eq!(Ok(180.0), eval("αθ", &scope));
+    assert_eq!(Ok(180.0), eval("alphatheta", &scope));
+    assert_eq!(
+        Err(Error::UnknownVariable("beta".to_string(), Span::new(0, 4))),
+        eval("beta", &scope)
+    );
+    assert_eq!(
+        Err(Error::UnknownVariable("ω".to_string(), Span::new(0, 2))),
+        eval("ω", &scope)
+    );
+}
+
+#[test]
+fn compile_without_scope() {
+    let names = Declarations::new().variable("xy").function("double");
+    let expr = compile_declared("2xy + double(z)", &names).unwrap();
+
+    let double: CustomFn = |args| Ok(args[0] * 2.0);
+    let scope = scope! { "xy" => 3, "z" => 4, "double" => double };
+    assert_eq!(Ok(()), expr.validate(&scope));
+    assert_eq!(Ok(14.0), expr.eval_with(&scope));
+
+    // Undeclared names are split as usual
+    let expr = compile_declared("xy", &Declarations::new()).unwrap();
+    assert_eq!(Ok(6.0), expr.eval_with(&scope! { "x" => 2, "y" => 3 }));
+
+    let expr = compile_declared("a + f(b)", &Declarations::new().function("f"))
+        .unwrap();
+    assert_eq!(
+        Err(vec![
+            Error::UnknownVariable("a".to_string(), Span::new(0, 1)),
+            Error::UnknownFunction("f".to_string(), Span::new(4, 8)),
+            Error::UnknownVariable("b".to_string(), Span::new(6, 7)),
+        ]),
+        expr.validate(&scope! {})
+    );
+    assert_eq!(
+        Ok(()),
+        expr.validate(
+            &Declarations::new()
+                .variable("a")
+                .variable("b")
+                .function("f")
+        )
+    );
+    assert_eq!(
+        Ok(3.0),
+        compile_declared("1 + 2", &Declarations::new())
+            .unwrap()
+            .eval()
+    );
+}
+
+#[test]
+fn owned_expressions() {
+    fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+    assert_shareable::<CompiledExpr>();
+
+    let scope = scope! { "x" => 2 };
+    let expr = compile("x + 1", &scope).unwrap().into_compiled();
+    let copy = expr.clone();
+    drop(scope);
+
+    assert_eq!(expr, copy);
+    assert_eq!(Ok(4.0), copy.eval_with(&scope! { "x" => 3 }));
+    assert_ne!(
+        expr,
+        compile_declared("x + 2", &Declarations::new()).unwrap()
+    );
+    assert_eq!(
+        expr,
+        compile_declared("x + 1", &Declarations::new()).unwrap()
+    );
+    // Spacing only moves the spans
+    assert_eq!(expr, compile_declared("x+1", &Declarations::new()).unwrap());
+    let scope = Scope::new();
+    assert!(
+        *compile("1+2", &scope).unwrap()
+            == *compile(" 1 + 2 ", &scope).unwrap()
+    );
+
+    let mut cache = std::collections::HashMap::new();
+    cache.insert("area", compile_declared("wh", &Declarations::new()));
+    let area = cache["area"].as_ref().unwrap();
+    assert_eq!(Ok(6.0), area.eval_with(&scope! { "w" => 2, "h" => 3 }));
+    assert!(format!("{:?}", area).starts_with("CompiledExpr"));
+}
+
+#[test]
+fn expression_trees() {
+    let tree = |expr| compile(expr, &Scope::new()).unwrap().to_expr();
+    let var = |name: &str| Box::new(Expr::Var(name.to_string()));
+
+    assert_eq!(
+        Expr::Unary {
+            op: UnaryOp::Neg,
+            operand: Box::new(Expr::Binary {
+                op: BinaryOp::Pow,
+                lhs: var("x"),
+                rhs: Box::new(Expr::Num(2.0)),
+            }),
+        },
+        tree("-x^2")
+    );
+    assert_eq!(
+        Expr::Conditional {
+            cond: Box::new(Expr::Unary {
+                op: UnaryOp::Not,
+                operand: var("a"),
+            }),
+            then: Box::new(Expr::Unary {
+                op: UnaryOp::Factorial,
+                operand: var("b"),
+            }),
+            otherwise: Box::new(Expr::Call {
+                name: "abs".to_string(),
+                args: vec![*var("c")],
+            }),
+        },
+        tree("!a ? b! : |c|")
+    );
+
+    let scope = scope! { "a" => 1, "b" => 3, "c" => -2, "x" => 4 };
+    for expr in &[
+        "-x^2 + 3x // 2",
+        "a && !b || c != 1",
+        "max(a, b, c) mod 2",
+        "x > 2 ? 5! : |c| * 10%",
+        "if(a, -b, c)",
+    ] {
+        let compiled = CompiledExpr::from(tree(expr));
+        assert_eq!(eval(expr, &scope), compiled.eval_with(&scope), "{}", expr);
+        assert_eq!(
+            compile_declared(expr, &Declarations::new()).unwrap(),
+            compiled,
+            "{}",
+            expr
+        );
+    }
+
+    let parser = Parser::builder()
+        .infix("<<", 45, Associativity::Left, |args| {
+            Ok(args[0] * 2f64.powf(args[1]))
+        })
+        .build()
+        .unwrap();
+    let shifted = parser.compile("1 << 3", &scope).unwrap().to_expr();
+    assert!(matches!(&shifted, Expr::Custom { op, args }
+        if op.symbol == "<<" && args.len() == 2));
+    assert_eq!(Ok(8.0), CompiledExpr::from(shifted.clone()).eval());
+
+    // Trees built by hand are checked like parsed ones
+    let call = |name: &str| Expr::Call {
+        name: name.to_string(),
+        args: vec![],
+    };
+    for name in &["sqrt", "max", "log"] {
+        assert_eq!(
+            Err(Error::NotEnoughFunctionParams(
+                name.to_string(),
+                Span::default()
+            )),
+            CompiledExpr::from(call(name)).eval()
+        );
+    }
+    if let Expr::Custom { op, mut args } = shifted {
+        args.pop();
+        assert_eq!(
+            Err(Error::MissingOperands("<<".to_string(), Span::default())),
+            CompiledExpr::from(Expr::Custom { op, args }).eval()
+        );
+    }
+}
+
+#[test]
+fn visitors() {
+    struct Calls(Vec<String>);
+
+    impl Visitor for Calls {
+        fn visit_call(&mut self, name: &str, _args: &[Expr]) {
+            self.0.push(name.to_string());
+        }
+    }
+
+    let expr = compile("sqrt(max(x, 2)) + |y|", &Scope::new())
+        .unwrap()
+        .to_expr();
+    let mut calls = Calls(Vec::new());
+    calls.visit_expr(&expr);
+    assert_eq!(calls.0, ["sqrt", "max", "abs"]);
+
+    // Renames every variable
+    struct Rename;
+
+    impl VisitorMut for Rename {
+        fn visit_var_mut(&mut self, name: &mut String) {
+            name.insert_str(0, "new_");
+        }
+    }
+
+    let mut expr = compile("x + 2y", &Scope::new()).unwrap().to_expr();
+    Rename.visit_expr_mut(&mut expr);
+    let expr = CompiledExpr::from(expr);
+    let scope = scope! { "new_x" => 1, "new_y" => 2 };
+    assert_eq!(Ok(5.0), expr.eval_with(&scope));
+}
+
+#[test]
+fn printing() {
+    let print = |expr: &str| {
+        let names = Declarations::new().variable("rate");
+        compile_declared(expr, &names).unwrap().to_string()
+    };
+
+    assert_eq!("-x^2", print("-x^2"));
+    assert_eq!("(-x)^2", print("(-x)^2"));
+    assert_eq!("2^-x", print("2^(-x)"));
+    assert_eq!("a - (b - c)", print("a - (b - c)"));
+    assert_eq!("a - b - c", print("((a - b) - c)"));
+    assert_eq!("2^3^4", print("2^(3^4)"));
+    assert_eq!("(2^3)^4", print("(2^3)^4"));
+    assert_eq!("(x!)!", print("(x!)!"));
+    assert_eq!("x!!", print("x!!"));
+    assert_eq!("5! - 1", print("5!-1"));
+    assert_eq!("a % (-b)", print("a % (-b)"));
+    assert_eq!("(x%) - 1", print("(x%) - 1"));
+    assert_eq!("(2 * x%) - 1", print("(2 * x%) - 1"));
+    assert_eq!("!(a && b)", print("!(a && b)"));
+    assert_eq!("a ? b : c ? d : e", print("a ? b : (c ? d : e)"));
+    assert_eq!("(a ? b : c) ? d : e", print("(a ? b : c) ? d : e"));
+    assert_eq!("2 * rate * x", print("(2rate) * x"));
+    assert_eq!("abs(x - 1) mod 3", print("|x - 1| mod 3"));
+    assert_eq!("max(a, b)", print("max(a,b)"));
+    assert_eq!("1e300 + 1.5e-7", print("1e300 + 0.00000015"));
+    assert_eq!("-6.02e23 * 123456789", print("-602e21 * 123456789"));
+    let scope = Scope::new();
+    let expr = compile("max(a,b)-(c)", &scope).unwrap();
+    assert_eq!("max(a, b) - c", format!("{}", expr));
+
+    let compact = PrintOptions {
+        spaced: false,
+        implicit_multiplication: true,
+    };
+    let print = |expr: &str| {
+        let names = Declarations::new().variable("rate");
+        compile_declared(expr, &names)
+            .unwrap()
+            .to_string_with(&compact)
+    };
+    assert_eq!("2rate*x", print("2 * rate * x"));
+    assert_eq!("-3(x+1)^2", print("-3 * (x + 1)^2"));
+    assert_eq!("2*e", print("2 * e"));
+    assert_eq!("x! == 1", print("x! == 1"));
+    assert_eq!("max(a,b)(x-1)", print("max(a, b) * (x - 1)"));
+
+    let parser = Parser::builder()
+        .infix("shl", 45, Associativity::Left, |args| {
+            Ok(args[0] * 2f64.powf(args[1]))
+        })
+        .build()
+        .unwrap();
+    let scope = Scope::new();
+    let expr = parser.compile("(1 shl 3) shl (2 shl 1)", &scope).unwrap();
+    assert_eq!("1 shl 3 shl (2 shl 1)", expr.to_string());
+
+    // Parts that failed to parse
+    let scope = Scope::new();
+    let print = |expr| compile_recovering(expr, &scope).0.to_string();
+    assert_eq!("?", print("1 2 3"));
+    assert_eq!("?", print("(1 + 2"));
+    assert_eq!("?", print("max(1,,2) + 1"));
+    let max = Expr::Call {
+        name: "max".to_string(),
+        args: vec![
+            Expr::Num(1.0),
+            Expr::Error(Error::EmptyExpression(Span::new(6, 6))),
+            Expr::Num(2.0),
+        ],
+    };
+    let sum = Expr::Binary {
+        op: BinaryOp::Add,
+        lhs: Box::new(max),
+        rhs: Box::new(Expr::Num(1.0)),
+    };
+    assert_eq!("max(1, ?, 2) + 1", sum.to_string());
+}
+
+#[test]
+fn postfix_before_minus() {
+    assert_eq!(Ok(119.0), eval("5! - 1", &Scope::new()));
+    assert_eq!(Ok(-0.5), eval("(50%) - 1", &Scope::new()));
+}
+
+// Small xorshift generator, so that failures are reproducible
+struct Random(u64);
+
+// Custom operators the generated trees use, with every fixity and
+// associativity
+const CUSTOM: [(&str, Fixity, i8, Associativity); 4] = [
+    ("<<", Fixity::Infix, 45, Associativity::Left),
+    ("nand", Fixity::Infix, 35, Associativity::Right),
+    ("~", Fixity::Prefix, 75, Associativity::Right),
+    ("°", Fixity::Postfix, 85, Associativity::Left),
+];
+
+const FIRST: CustomFn = |args| Ok(args[0]);
+
+fn custom_operator(i: usize) -> CustomOperator {
+    let (symbol, fixity, precedence, associativity) = CUSTOM[i];
+    CustomOperator {
+        symbol: symbol.to_string(),
+        fixity,
+        precedence,
+        associativity,
+        eval: FIRST,
+    }
+}
+
+// The parser reads a negative number back as the negation of its magnitude
+struct NegativeAsNegation;
+
+impl VisitorMut for NegativeAsNegation {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Num(n) if n.is_sign_negative() => {
+                *expr = Expr::Unary {
+                    op: UnaryOp::Neg,
+                    operand: Box::new(Expr::Num(-*n)),
+                };
+            },
+            _ => walk_expr_mut(self, expr),
+        }
+    }
+}
+
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    fn child(&mut self, depth: usize) -> Box<Expr> {
+        Box::new(self.expr(depth - 1))
+    }
+
+    fn expr(&mut self, depth: usize) -> Expr {
+        const NUMBERS: [f64; 8] = [0.0, 1.0, 2.0, 2.5, 10.0, 0.125, -1.0, -2.5];
+        const NAMES: [&str; 5] = ["a", "b", "x", "y", "rate"];
+        const UNARY: [UnaryOp; 5] = [
+            UnaryOp::Neg,
+            UnaryOp::Not,
+            UnaryOp::Factorial,
+            UnaryOp::DoubleFactorial,
+            UnaryOp::Percent,
+        ];
+        const BINARY: [BinaryOp; 17] = [
+            BinaryOp::Add,
+            BinaryOp::Sub,
+            BinaryOp::Mul,
+            BinaryOp::Div,
+            BinaryOp::Rem,
+            BinaryOp::Mod,
+            BinaryOp::FloorDiv,
+            BinaryOp::Pow,
+            BinaryOp::Gt,
+            BinaryOp::Lt,
+            BinaryOp::Ge,
+            BinaryOp::Le,
+            BinaryOp::Eq,
+            BinaryOp::Ne,
+            BinaryOp::And,
+            BinaryOp::Or,
+            BinaryOp::Xor,
+        ];
+
+        match if depth == 0 {
+            self.below(2)
+        }
+        else {
+            self.below(9)
+        } {
+            0 => Expr::Num(NUMBERS[self.below(NUMBERS.len())]),
+            1 => Expr::Var(NAMES[self.below(NAMES.len())].to_string()),
+            2 => Expr::Unary {
+                op: UNARY[self.below(UNARY.len())],
+                operand: self.child(depth),
+            },
+            3 => Expr::Conditional {
+                cond: self.child(depth),
+                then: self.child(depth),
+                otherwise: self.child(depth),
+            },
+            4 => {
+                let (name, arity) =
+                    [("max", 2), ("sqrt", 1), ("if", 3)][self.below(3)];
+                let args = (0..arity).map(|_| self.expr(depth - 1)).collect();
+                Expr::Call {
+                    name: name.to_string(),
+                    args,
+                }
+            },
+            5 => {
+                let op = custom_operator(self.below(CUSTOM.len()));
+                let arity = if op.fixity == Fixity::Infix { 2 } else { 1 };
+                let args = (0..arity).map(|_| self.expr(depth - 1)).collect();
+                Expr::Custom { op, args }
+            },
+            _ => Expr::Binary {
+                op: BINARY[self.below(BINARY.len())],
+                lhs: self.child(depth),
+                rhs: self.child(depth),
+            },
+        }
+    }
+}
+
+#[test]
+fn printing_round_trips() {
+    let names = Declarations::new().variable("rate");
+    let mut parser = Parser::builder();
+    for &(symbol, fixity, precedence, associativity) in &CUSTOM {
+        parser = match fixity {
+            Fixity::Infix => {
+                parser.infix(symbol, precedence, associativity, FIRST)
+            },
+            Fixity::Prefix => parser.prefix(symbol, precedence, FIRST),
+            Fixity::Postfix => parser.postfix(symbol, precedence, FIRST),
+        };
+    }
+    let parser = parser.build().unwrap();
+    let options = [
+        PrintOptions::default(),
+        PrintOptions {
+            spaced: false,
+            implicit_multiplication: false,
+        },
+        PrintOptions {
+            spaced: true,
+            implicit_multiplication: true,
+        },
+        PrintOptions {
+            spaced: false,
+            implicit_multiplication: true,
+        },
+    ];
+
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+    for _ in 0..2000 {
+        let expr = random.expr(4);
+        let mut expected = expr.clone();
+        NegativeAsNegation.visit_expr_mut(&mut expected);
+        for options in &options {
+            let text = expr.to_string_with(options);
+            let parsed = parser
+                .compile_declared(&text, &names)
+                .unwrap_or_else(|err| panic!("{}: {:?}", text, err));
+            assert_eq!(expected, parsed.to_expr(), "{}", text);
+        }
+    }
+
+    for expr in &["x⁻¹", "2x⁻²"] {
+        let parsed = compile_declared(expr, &names).unwrap();
+        let text = parsed.to_string();
+        assert_eq!(parsed, compile_declared(&text, &names).unwrap(), "{}", text);
+    }
+}
+
+#[test]
+fn latex() {
+    let latex = |expr: &str| {
+        let names = Declarations::new()
+            .variable("rate")
+            .variable("theta_max")
+            .function("my_fn");
+        compile_declared(expr, &names).unwrap().to_latex()
+    };
+
+    assert_eq!(r"\frac{a + 1}{b}", latex("(a + 1) / b"));
+    assert_eq!(r"\frac{\frac{a}{b}}{c}", latex("a / b / c"));
+    assert_eq!(r"\left(\frac{a}{b}\right)^{2}", latex("(a / b)^2"));
+    assert_eq!(r"x^{y^{2}}", latex("x^y^2"));
+    assert_eq!(r"\left(x^{y}\right)^{2}", latex("(x^y)^2"));
+    assert_eq!(r"-x^{2}", latex("-x^2"));
+    assert_eq!(r"\left(-x\right)^{2}", latex("(-x)^2"));
+    assert_eq!(r"a - \left(-b\right)", latex("a - -b"));
+    assert_eq!(r"a - \left(b + c\right)", latex("a - (b + c)"));
+    assert_eq!(r"2x \cdot 3", latex("2x * 3"));
+    assert_eq!(r"2\pi \cdot \mathrm{rate}", latex("2PI * rate"));
+    assert_eq!(r"\left(a + b\right)!", latex("(a + b)!"));
+    assert_eq!(r"\sqrt{x} + \sqrt[3]{8}", latex("sqrt(x) + cbrt(8)"));
+    assert_eq!(r"\log_{2}\left(x\right)", latex("log(2, x)"));
+    assert_eq!(r"\left|x - 1\right|", latex("|x - 1|"));
+    assert_eq!(r"\mathrm{e}^{-x}", latex("E^-x"));
+    assert_eq!(r"o + \omega", latex("omicron + ω"));
+    assert_eq!(r"a \leq b \land a \neq c", latex("a <= b && a != c"));
+    assert_eq!(r"\neg \left(a \lor b\right)", latex("!(a || b)"));
+    assert_eq!(r"\theta_{\mathrm{max}} \geq 50\%", latex("θ_max >= 50%"));
+    assert_eq!(r"\left(50\%\right) - x", latex("(50%) - x"));
+    assert_eq!(
+        r"\operatorname{my\_fn}\left(x, 1\right)",
+        latex("my_fn(x, 1)")
+    );
+    assert_eq!(
+        r"\begin{cases} 1 & \text{if } x > 0 \\ 0 & \text{otherwise} \end{cases}",
+        latex("x > 0 ? 1 : 0")
+    );
+    assert_eq!(
+        r"1 \times 10^{300} + 1.5 \times 10^{-7}",
+        latex("1e300 + 1.5e-7")
+    );
+    assert_eq!(r"\left(2 \times 10^{20}\right)^{2}", latex("2e20^2"));
+    assert_eq!(r"a \cdot \left(2 \times 10^{20}\right)", latex("a * 2e20"));
+    assert_eq!(r"2 \times 10^{20} \cdot a", latex("2e20 * a"));
+    assert_eq!(r"-6.02 \times 10^{23}", latex("-6.02e23"));
+
+    let parser = Parser::new(ParserOptions {
+        syntax: Syntax::AsciiMath,
+        ..ParserOptions::default()
+    });
+    let scope = Scope::new();
+    let expr = parser.compile("sum_(i=1)^n (i + 1)", &scope).unwrap();
+    assert_eq!(r"\sum_{i = 1}^{n} \left(i + 1\right)", expr.to_latex());
+
+    let parser = Parser::builder()
+        .infix("^^", 45, Associativity::Left, |args| Ok(args[0] + args[1]))
+        .infix("~>", 45, Associativity::Left, |args| Ok(args[0] + args[1]))
+        .postfix("\\", 80, |args| Ok(args[0]))
+        .build()
+        .unwrap();
+    let scope = Scope::new();
+    let expr = parser.compile(r"a ^^ b ~> c\", &scope).unwrap();
+    assert_eq!(
+        r"a \mathbin{\^{}\^{}} b \mathbin{\textasciitilde{}>} c\backslash ",
+        expr.to_latex()
+    );
+}
+
+#[test]
+fn mathml() {
+    let names = Declarations::new()
+        .variable("rate")
+        .variable("theta_max")
+        .function("my_fn");
+    let fixtures = include_str!("fixtures/mathml.txt");
+    let cases = fixtures
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    for case in cases.split("\n\n").filter(|case| !case.trim().is_empty()) {
+        let (expr, expected) = case.trim().split_once('\n').unwrap();
+        let compiled = compile_declared(expr, &names).unwrap();
+        assert_eq!(expected, compiled.to_mathml(), "{}", expr);
+    }
+
+    let parser = Parser::new(ParserOptions {
+        syntax: Syntax::AsciiMath,
+        ..ParserOptions::default()
+    });
+    let scope = Scope::new();
+    let expr = parser.compile("sum_(i=1)^n i^2", &scope).unwrap();
+    assert_eq!(
+        expr.to_mathml(),
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow>\
+         <munderover><mo>\u{2211}</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn>\
+         </mrow><mi>n</mi></munderover><msup><mi>i</mi><mn>2</mn></msup>\
+         </mrow></math>"
+    );
+
+    let parser = Parser::builder()
+        .infix("<&>", 45, Associativity::Left, |args| Ok(args[0] + args[1]))
+        .build()
+        .unwrap();
+    let scope = Scope::new();
+    let expr = parser.compile("a <&> b", &scope).unwrap();
+    assert!(expr.to_mathml().contains("<mo>&lt;&amp;&gt;</mo>"));
+}
+
+#[test]
+fn series() {
+    let scope = scope! { "n" => 4 };
+    let eval = |expr| asciimath(expr, &scope);
+    assert_eq!(Ok(30.0), eval("sum_(i=1)^n i^2"));
+    assert_eq!(Ok(24.0), eval("prod_(k=1)^n k"));
+    // Bounds are rounded inwards, and an empty range is the identity
+    assert_eq!(Ok(5.0), eval("sum_(i=1.5)^3.5 i"));
+    assert_eq!(Ok(0.0), eval("sum_(i=5)^1 i"));
+    assert_eq!(Ok(1.0), eval("prod_(i=5)^1 i"));
+    // Ranges that would not finish
+    assert!(eval("sum_(i=1)^oo i").unwrap().is_nan());
+    assert!(eval("sum_(i=1)^(2^60) i").unwrap().is_nan());
+    // The index shadows a variable of the same name
+    assert_eq!(Ok(6.0), asciimath("sum_(i=1)^3 i", &scope! { "i" => 100 }));
+
+    // The index is only known within the body
+    let parser = Parser::new(ParserOptions {
+        syntax: Syntax::AsciiMath,
+        ..ParserOptions::default()
+    });
+    let names = Declarations::new();
+    let expr = parser.compile_declared("sum_(i=1)^3 i j + i", &names).unwrap();
+    assert_eq!(
+        Err(vec![
+            Error::UnknownVariable("j".to_string(), Span::new(14, 15)),
+            Error::UnknownVariable("i".to_string(), Span::new(18, 19)),
+        ]),
+        expr.validate(&names)
+    );
+
+    let expr = parser.compile("sum_(i=1)^n i^2", &scope).unwrap();
+    let square = Expr::Binary {
+        op: BinaryOp::Pow,
+        lhs: Box::new(Expr::Var("i".to_string())),
+        rhs: Box::new(Expr::Num(2.0)),
+    };
+    let series = Expr::Series {
+        op: SeriesOp::Sum,
+        index: "i".to_string(),
+        from: Box::new(Expr::Num(1.0)),
+        to: Box::new(Expr::Var("n".to_string())),
+        body: Box::new(square),
+    };
+    assert_eq!(series, expr.to_expr());
+    assert_eq!(Ok(30.0), CompiledExpr::from(series).eval_with(&scope));
+    assert_eq!("sum(i, 1, n, i^2)", expr.to_string());
+
+    // The plain syntax has no series
+    assert_eq!(
+        Err(Error::UnexpectedToken(",".to_string(), Span::new(5, 6))),
+        asciimath::eval("sum(i, 1, 3, i)", &Scope::new())
+    );
+}
+
+fn asciimath(expr: &str, scope: &Scope) -> Result<f64, Error> {
+    let parser = Parser::new(ParserOptions {
+        syntax: Syntax::AsciiMath,
+        ..ParserOptions::default()
+    });
+    parser.eval(expr, scope)
+}
+
+#[test]
+fn asciimath_notation() {
+    let scope = scope! { "x" => 4, "n" => 3, "a" => 6, "b" => 2 };
+    let eval = |expr| asciimath(expr, &scope);
+
+    assert_eq!(Ok(3.0), eval("frac(a)(b)"));
+    assert_eq!(Ok(2.0), eval("sqrt x"));
+    assert_eq!(Ok(4.0), eval("sqrt x^2"));
+    assert_eq!(Ok(3.0), eval("root(3)(27)"));
+    assert_eq!(Ok(2.0), eval("root(4)(16)"));
+    assert_eq!(Ok(14.0), eval("sum_(i=1)^n i^2"));
+    assert_eq!(Ok(15.0), eval("sum_(i=1)^n i^2 + 1"));
+    assert_eq!(Ok(24.0), eval("prod_(k=1)^4 k"));
+    assert_eq!(Ok(f64::consts::PI), eval("pi"));
+    assert_eq!(Ok(f64::INFINITY), eval("oo"));
+    assert_eq!(Ok(1.0), eval("ln e"));
+    assert_eq!(Ok(1.0), eval("2 le 3 and 1 ne 2"));
+    assert_eq!(Ok(3.0), eval("abs(-3) + |x - 4|"));
+    assert_eq!(Ok(2.0), eval("floor(2.5)"));
+    assert_eq!(Ok(2.0), eval("|__2.5__|"));
+    assert_eq!(Ok(3.0), eval("lceil 2.1 rceil"));
+    assert_eq!(Ok(6.0), eval("max(a, b)"));
+    assert_eq!(Ok(3.0), eval("log_2 8"));
+    assert_eq!(Ok(2.0), eval("log 100"));
+    assert_eq!(Ok(12.0), eval("2xx3 + a-:b * 2"));
+    assert_eq!(Ok(12.0), eval("ab"));
+    assert_eq!(Ok(-0.5), eval("(50%) - 1"));
+    assert_eq!(Ok(0.0), eval("a % b"));
+    assert_eq!(Ok(1.0), eval("10 % -3"));
+
+    let x_i = scope! { "x_i" => 2, "x_max" => 3 };
+    assert_eq!(Ok(5.0), asciimath("x_i + x_(max)", &x_i));
+}
+
+#[test]
+fn asciimath_grouping() {
+    let scope = Scope::new();
+    let parser = Parser::new(ParserOptions {
+        syntax: Syntax::AsciiMath,
+        ..ParserOptions::default()
+    });
+    let print = |expr| parser.compile(expr, &scope).unwrap().to_string();
+
+    // `/` divides the terms directly around it
+    assert_eq!("1 / 2 * x", print("1/2x"));
+    assert_eq!("a + b / c + d", print("a+b/c+d"));
+    assert_eq!("2 * (x^2 / 3)", print("2x^2/3"));
+    assert_eq!("(a + b) / (c + d)", print("(a+b)/(c+d)"));
+    assert_eq!("a / b / c", print("a/b/c"));
+    // A function applies to the term after it
+    assert_eq!("sin(x^2)", print("sin x^2"));
+    assert_eq!("sin(x)^2", print("sin^2 x"));
+    assert_eq!("sin(x) * 2", print("sin x xx 2"));
+    assert_eq!("-x^2 * y^-1", print("-x^2 y^-1"));
+}
+
+#[test]
+fn asciimath_disabled_operators() {
+    let parser = Parser::builder()
+        .options(ParserOptions {
+            syntax: Syntax::AsciiMath,
+            ..ParserOptions::default()
+        })
+        .disable("^")
+        .disable("*")
+        .build()
+        .unwrap();
+    let scope = scope! { "x" => 2 };
+
+    assert_eq!(
+        Err(Error::InvalidToken("^".to_string(), Span::new(1, 2))),
+        parser.eval("2^3", &scope)
+    );
+    assert_eq!(
+        Err(Error::InvalidToken("xx".to_string(), Span::new(2, 4))),
+        parser.eval("x xx 2", &scope)
+    );
+    // A left-out multiplication is not an operator that was written
+    assert_eq!(Ok(4.0), parser.eval("2x", &scope));
+    assert_eq!(Ok(-2.0), parser.eval("-x", &scope));
+}
+
+#[test]
+fn asciimath_errors() {
+    let scope = scope! { "x" => 1 };
+    let eval = |expr| asciimath(expr, &scope);
+    let unsupported = |text: &str, start, end| {
+        Err(Error::UnsupportedSyntax(
+            text.to_string(),
+            Span::new(start, end),
+        ))
+    };
+
+    assert_eq!(unsupported("text", 0, 4), eval("text(hi)"));
+    assert_eq!(unsupported("vec", 4, 7), eval("2 + vec x"));
+    assert_eq!(unsupported("->", 2, 4), eval("x -> 2"));
+    assert_eq!(unsupported("int", 0, 3), eval("int x dx"));
+    assert_eq!(unsupported("\"a\"", 4, 7), eval("1 + \"a\""));
+    assert_eq!(unsupported("x_(i+1)", 0, 7), eval("x_(i+1)"));
+    assert_eq!(unsupported("x_π", 0, 4), eval("x_π"));
+    assert_eq!(unsupported("x_²", 0, 4), eval("x_²"));
+    assert_eq!(unsupported("sin^-1", 0, 6), eval("sin^-1 x"));
+    assert_eq!(unsupported("sum", 0, 3), eval("sum x"));
+    assert_eq!(
+        Err(Error::MissingOperands("frac".to_string(), Span::new(0, 4))),
+        eval("frac(1)")
+    );
+    assert_eq!(
+        Err(Error::NotEnoughFunctionParams(
+            "sin".to_string(),
+            Span::new(0, 3)
+        )),
+        eval("sin")
+    );
+    assert_eq!(
+        Err(Error::InvalidToken("#".to_string(), Span::new(2, 3))),
+        eval("x #")
+    );
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(2, 3))),
+        eval("(x]")
+    );
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(8, 10))),
+        eval("|__x + 1~|")
+    );
+    // Not `n || m * x`
+    let norms = scope! { "n" => 3, "m" => 0, "x" => -4 };
+    assert_eq!(Ok(4.0), asciimath("norm(x)", &norms));
+    assert_eq!(Ok(4.0), asciimath("norm x", &norms));
+
+    let disabled = Parser::new(ParserOptions {
+        syntax: Syntax::AsciiMath,
+        implicit_multiplication: ImplicitMultiplication::Disabled,
+        ..ParserOptions::default()
+    });
+    assert_eq!(
+        Err(Error::ImplicitMultiplication(Span::new(1, 1))),
+        disabled.eval("2x", &scope)
+    );
+    assert_eq!(Ok(2.0), disabled.eval("frac(2)(x)", &scope));
+}
+
+fn latex_parser() -> Parser {
+    Parser::new(ParserOptions {
+        syntax: Syntax::Latex,
+        ..ParserOptions::default()
+    })
+}
+
+#[test]
+fn latex_input() {
+    let scope = scope! { "x" => 4, "n" => 3, "a" => 6, "b" => 2, "rate" => 5 };
+    let parser = latex_parser();
+    let eval = |expr| parser.eval(expr, &scope);
+
+    assert_eq!(Ok(3.0), eval(r"\frac{a}{b}"));
+    assert_eq!(Ok(0.5), eval(r"\frac12"));
+    assert_eq!(Ok(2.0), eval(r"\sqrt{x}"));
+    assert_eq!(Ok(3.0), eval(r"\sqrt[3]{27}"));
+    assert_eq!(Ok(2.0), eval(r"\sqrt[4]{16}"));
+    assert_eq!(Ok(16.0), eval(r"x^{2}"));
+    // An unbraced argument is a single digit
+    assert_eq!(Ok(48.0), eval(r"x^23"));
+    assert_eq!(Ok(12.0), eval(r"a \cdot b"));
+    assert_eq!(Ok(12.0), eval(r"a \times b"));
+    assert_eq!(Ok(16.0), eval(r"\left( a + b \right) \cdot \frac{b}{1}"));
+    assert_eq!(Ok(1.0), eval(r"\sin 90"));
+    assert_eq!(Ok(1.0), eval(r"\sin^{2} 45 + \cos^2 45"));
+    assert_eq!(Ok(1.0), eval(r"\ln e"));
+    assert_eq!(Ok(3.0), eval(r"\log_{2} 8"));
+    assert_eq!(Ok(2.0), eval(r"\log(100)"));
+    assert_eq!(Ok(f64::consts::PI * 2.0), eval(r"2\pi"));
+    assert_eq!(Ok(1.0), eval(r"b \le a \land a \ge b \land a \neq b"));
+    assert_eq!(Ok(1.0), eval(r"\left|x - 5\right|"));
+    assert_eq!(
+        Ok(5.0),
+        eval(r"\lfloor 2.5 \rfloor + \left\lceil 2.1 \right\rceil")
+    );
+    assert_eq!(Ok(6.0), eval(r"\max(a, b)"));
+    assert_eq!(Ok(14.0), eval(r"\sum_{i=1}^{n} i^{2}"));
+    assert_eq!(Ok(24.0), eval(r"\prod_{k=1}^{4} k"));
+    assert_eq!(Ok(10.0), eval(r"\mathrm{rate} \, b"));
+    assert_eq!(Ok(0.0), eval(r"a \mathbin{\%} b + a \bmod b"));
+    assert_eq!(Ok(-0.5), eval(r"\left(50\%\right) - 1"));
+    assert_eq!(Ok(2.0), eval(r"a \% -4"));
+
+    let subscripts = scope! { "x_1" => 2, "theta_max" => 3 };
+    assert_eq!(
+        Ok(6.0),
+        parser.eval(r"x_{1} \cdot \theta_{\mathrm{max}}", &subscripts)
+    );
+}
+
+#[test]
+fn latex_input_errors() {
+    let scope = scope! { "x" => 1 };
+    let parser = latex_parser();
+    let eval = |expr| parser.eval(expr, &scope);
+    let unsupported = |text: &str, start, end| {
+        Err(Error::UnsupportedSyntax(
+            text.to_string(),
+            Span::new(start, end),
+        ))
+    };
+
+    assert_eq!(unsupported(r"\int", 0, 4), eval(r"\int x dx"));
+    assert_eq!(unsupported(r"\vec", 4, 8), eval(r"2 + \vec{x}"));
+    // Text is prose, even when it spells a name
+    assert_eq!(unsupported(r"\text{x}", 0, 8), eval(r"\text{x}"));
+    assert_eq!(unsupported(r"\textrm{x}", 4, 14), eval(r"2 + \textrm{x}"));
+    assert_eq!(unsupported(r"\sin^{-1}", 0, 9), eval(r"\sin^{-1} x"));
+    assert_eq!(unsupported("x_{i+1}", 0, 7), eval("x_{i+1}"));
+    assert_eq!(unsupported("x_{1.5}", 0, 7), eval("x_{1.5}"));
+    assert_eq!(unsupported("&", 2, 3), eval("x & 1"));
+    assert_eq!(
+        Err(Error::MissingOperands(
+            r"\frac".to_string(),
+            Span::new(0, 5)
+        )),
+        eval(r"\frac{1}")
+    );
+    assert_eq!(
+        Err(Error::NotEnoughFunctionParams(
+            "sin".to_string(),
+            Span::new(0, 4)
+        )),
+        eval(r"\sin")
+    );
+    assert_eq!(
+        Err(Error::UnbalancedParentheses(Span::new(0, 6))),
+        eval(r"\left( x")
+    );
+}
+
+#[test]
+fn latex_parser_options() {
+    let scope = scope! { "x" => 2, "r" => 3 };
+    let options = ParserOptions {
+        syntax: Syntax::Latex,
+        implicit_multiplication: ImplicitMultiplication::NumbersAndParens,
+        ..ParserOptions::default()
+    };
+    let parser = Parser::builder()
+        .options(options.clone())
+        .disable("*")
+        .disable("/")
+        .build()
+        .unwrap();
+    let eval = |expr| parser.eval(expr, &scope);
+
+    assert_eq!(
+        Err(Error::InvalidToken(r"\cdot".to_string(), Span::new(2, 7))),
+        eval(r"x \cdot 2")
+    );
+    assert_eq!(
+        Err(Error::InvalidToken(r"\frac".to_string(), Span::new(0, 5))),
+        eval(r"\frac{1}{2}")
+    );
+    assert_eq!(Ok(4.0), eval("2x"));
+    assert_eq!(Ok(6.0), eval(r"x\left(r\right)"));
+    assert_eq!(Ok(6.0), eval("{x}r"));
+    assert_eq!(
+        Err(Error::ImplicitMultiplication(Span::new(1, 1))),
+        eval("xr")
+    );
+    assert_eq!(
+        Err(Error::ImplicitMultiplication(Span::new(4, 4))),
+        eval(r"\pi r")
+    );
+
+    let asciimath = Parser::new(ParserOptions {
+        syntax: Syntax::AsciiMath,
+        ..options
+    });
+    assert_eq!(Ok(6.0), asciimath.eval("x(r)", &scope));
+    assert_eq!(
+        Err(Error::ImplicitMultiplication(Span::new(1, 1))),
+        asciimath.eval("xr", &scope)
+    );
+}
+
+#[test]
+fn latex_rejects_custom_operators() {
+    let shl: CustomFn = |args| Ok(args[0] * 2f64.powf(args[1]));
+    let built = Parser::builder()
+        .infix("<<", 45, Associativity::Left, shl)
+        .options(ParserOptions {
+            syntax: Syntax::Latex,
+            ..ParserOptions::default()
+        })
+        .build();
+    assert_eq!(
+        Some(Error::CustomOperatorSyntax("<<".to_string())),
+        built.err()
+    );
+}
+
+#[test]
+fn latex_round_trips() {
+    let names = Declarations::new().variable("rate");
+    let parser = latex_parser();
+
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+    for _ in 0..2000 {
+        let expr = random.expr(4);
+        let latex = expr.to_latex();
+        // Conditionals and custom operators have no LaTeX input form
+        if latex.contains("cases") || has_custom(&expr) {
+            continue;
+        }
+        let parsed = parser
+            .compile_declared(&latex, &names)
+            .unwrap_or_else(|err| panic!("{}: {:?}", latex, err));
+        // `a // b` reads back as `floor(a / b)`, which is set the same way
+        let mut expected = expr.clone();
+        FloorDivAsFloor.visit_expr_mut(&mut expected);
+        NegativeAsNegation.visit_expr_mut(&mut expected);
+        assert_eq!(expected, parsed.to_expr(), "{}", latex);
+    }
+
+    let scope = Scope::new();
+    let read = |expr: &str| parser.compile(expr, &scope).unwrap().to_string();
+    assert_eq!("floor(7 / 2)", read(&typeset("7 // 2")));
+    assert_eq!("-INFINITY", read(&typeset("NEG_INFINITY")));
+    assert_eq!("E", read(&typeset("E")));
+    let euler = Declarations::new().variable("e");
+    let upright = parser.compile_declared(r"\mathrm{e}^{e}", &euler).unwrap();
+    assert_eq!("E^e", upright.to_string());
+    assert!(matches!(
+        parser.eval(&typeset("if(1, 2, 3)"), &scope),
+        Err(Error::UnsupportedSyntax(..))
+    ));
+}
+
+fn has_custom(expr: &Expr) -> bool {
+    matches!(expr, Expr::Custom { .. })
+        || expr.children().into_iter().any(has_custom)
+}
+
+struct FloorDivAsFloor;
+
+impl VisitorMut for FloorDivAsFloor {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Binary {
+            op: BinaryOp::FloorDiv,
+            lhs,
+            rhs,
+        } = expr
+        {
+            let quotient = Expr::Binary {
+                op: BinaryOp::Div,
+                lhs: lhs.clone(),
+                rhs: rhs.clone(),
+            };
+            *expr = Expr::Call {
+                name: "floor".to_string(),
+                args: vec![quotient],
+            };
+        }
+        asciimath::walk_expr_mut(self, expr);
+    }
+}
+
+fn typeset(expr: &str) -> String {
+    compile(expr, &Scope::new()).unwrap().to_latex()
+}
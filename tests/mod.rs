@@ -1 +1,2 @@
+#![allow(special_module_name)]
 mod lib;